@@ -0,0 +1,74 @@
+-- users.sqlite, orders.sqlite, archive.sqlite 를 data/jjambot.sqlite 하나로 합침
+
+CREATE TABLE users(
+    id INTEGER PRIMARY KEY,
+    userid INTEGER not null UNIQUE,
+    username INTEGER DEFAULT null,
+    realname INTEGER DEFAULT null,
+    admin INTEGER NOT NULL CHECK(admin IN (0, 1)) DEFAULT 0,
+    unit TEXT DEFAULT null
+);
+
+CREATE TABLE timer(
+    id INTEGER PRIMARY KEY,
+    userid INTEGER not null,
+    timewhen TEXT not null,
+    what TEXT CHECK(what IN ('breakfast', 'breakfastorder', 'nextbreakfast', 'nextbreakfastorder', 'lunch', 'lunchorder', 'dinner', 'dinnerorder', 'brunch', 'brunchorder', 'sundaybreakfast', 'sundaybreakfastorder')),
+    UNIQUE (userid, what),
+    FOREIGN KEY (userid) REFERENCES users(userid)
+);
+
+CREATE TABLE allergies(
+    id INTEGER PRIMARY KEY,
+    userid INTEGER not null,
+    allergen INTEGER not null CHECK(allergen BETWEEN 1 AND 19),
+    UNIQUE (userid, allergen),
+    FOREIGN KEY (userid) REFERENCES users(userid)
+);
+
+CREATE TABLE prefs(
+    id INTEGER PRIMARY KEY,
+    userid INTEGER not null,
+    tag TEXT not null,
+    until TEXT DEFAULT null,
+    UNIQUE (userid, tag),
+    FOREIGN KEY (userid) REFERENCES users(userid)
+);
+
+-- 식사순서 기록 (예전 orders.sqlite 는 floor 대신 orderidx 를 검사하고 있었음)
+CREATE TABLE orders(
+    id INTEGER PRIMARY KEY,
+    datestime TEXT,
+    floor INTEGER CHECK(floor IN (0, 1)),
+    ordertxt TEXT,
+    orderidx INTEGER CHECK(orderidx IN (0, 1, 2))
+);
+
+-- 부대별 마지막 식단 검사 결과
+CREATE TABLE quality(
+    id INTEGER PRIMARY KEY,
+    unit TEXT NOT NULL,
+    checked TEXT NOT NULL,
+    dates TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    detail TEXT NOT NULL
+);
+
+-- 지금까지 받은 식단 (부대, 날짜별 요리)
+CREATE TABLE archive(
+    id INTEGER PRIMARY KEY,
+    unit TEXT NOT NULL,
+    dates TEXT NOT NULL,
+    meal TEXT NOT NULL,
+    name TEXT NOT NULL,
+    allergens TEXT,
+    kcal REAL,
+    seen TEXT NOT NULL
+);
+CREATE INDEX archive_unit_dates ON archive(unit, dates);
+
+-- 예전 파일 가져오기 같은 한 번만 하는 작업 기록
+CREATE TABLE meta(
+    key TEXT PRIMARY KEY,
+    value TEXT
+);
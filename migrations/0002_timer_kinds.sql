@@ -0,0 +1,21 @@
+-- 예약 종류는 src/kinds.rs 의 표로 관리하므로 timer.what 의 CHECK 목록을 없앰
+-- SQLite 는 제약만 지울 수 없어서 테이블을 새로 만들어 옮김
+-- 예전 코드는 아침메뉴를 breakfastorder 로, 아침식집을 breakfast 로 뒤바꿔 저장했으므로 옮기면서 바로잡음
+
+CREATE TABLE timer_new(
+    id INTEGER PRIMARY KEY,
+    userid INTEGER not null,
+    timewhen TEXT not null,
+    what TEXT not null,
+    UNIQUE (userid, what),
+    FOREIGN KEY (userid) REFERENCES users(userid)
+);
+
+INSERT INTO timer_new (id, userid, timewhen, what)
+SELECT id, userid, timewhen,
+    CASE what WHEN 'breakfast' THEN 'breakfastorder' WHEN 'breakfastorder' THEN 'breakfast' ELSE what END
+FROM timer WHERE what IS NOT NULL;
+
+DROP TABLE timer;
+
+ALTER TABLE timer_new RENAME TO timer;
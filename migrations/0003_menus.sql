@@ -0,0 +1,30 @@
+-- 부대별로 받은 식단을 jjam-<부대코드>-<날짜>.sqlite 스냅샷 대신 여기 둠 (열은 스냅샷의 jjam, dishes 에 unit 만 더함)
+-- 예전 스냅샷은 처음 시작할 때 한 번만 가져옴 (src/db.rs)
+
+CREATE TABLE menus(
+    id INTEGER PRIMARY KEY,
+    unit TEXT NOT NULL,
+    dates TEXT NOT NULL,
+    brst TEXT,
+    brst_cal REAL,
+    lunc TEXT,
+    lunc_cal REAL,
+    dinr TEXT,
+    dinr_cal REAL,
+    adspcfd TEXT,
+    adspcfd_cal REAL,
+    sum_cal REAL
+);
+CREATE INDEX menus_unit_dates ON menus(unit, dates);
+
+-- menus 의 끼니 칸을 요리 단위로 나눈 것 (검색, 칼로리 조회용)
+CREATE TABLE dishes(
+    id INTEGER PRIMARY KEY,
+    unit TEXT NOT NULL,
+    dates TEXT NOT NULL,
+    meal TEXT NOT NULL,
+    name TEXT NOT NULL,
+    allergens TEXT,
+    kcal REAL
+);
+CREATE INDEX dishes_unit_dates ON dishes(unit, dates);
@@ -0,0 +1,64 @@
+-- DATABASE_URL 이 postgres:// 일 때 쓰는 스키마
+-- 사용자, 예약, 식사순서, 식단만 여기 두고 나머지는 data/jjambot.sqlite 에 그대로 둠 (열 이름은 SQLite 쪽과 같음)
+
+CREATE TABLE users(
+    id BIGSERIAL PRIMARY KEY,
+    userid BIGINT NOT NULL UNIQUE,
+    username TEXT DEFAULT NULL,
+    realname TEXT DEFAULT NULL,
+    admin INTEGER NOT NULL CHECK(admin IN (0, 1)) DEFAULT 0,
+    unit TEXT DEFAULT NULL
+);
+
+CREATE TABLE timer(
+    id BIGSERIAL PRIMARY KEY,
+    userid BIGINT NOT NULL REFERENCES users(userid),
+    timewhen TEXT NOT NULL,
+    what TEXT CHECK(what IN ('breakfast', 'breakfastorder', 'nextbreakfast', 'nextbreakfastorder', 'lunch', 'lunchorder', 'dinner', 'dinnerorder', 'brunch', 'brunchorder', 'sundaybreakfast', 'sundaybreakfastorder')),
+    UNIQUE (userid, what)
+);
+
+CREATE TABLE allergies(
+    id BIGSERIAL PRIMARY KEY,
+    userid BIGINT NOT NULL REFERENCES users(userid),
+    allergen INTEGER NOT NULL CHECK(allergen BETWEEN 1 AND 19),
+    UNIQUE (userid, allergen)
+);
+
+CREATE TABLE prefs(
+    id BIGSERIAL PRIMARY KEY,
+    userid BIGINT NOT NULL REFERENCES users(userid),
+    tag TEXT NOT NULL,
+    until TEXT DEFAULT NULL,
+    UNIQUE (userid, tag)
+);
+
+CREATE TABLE orders(
+    id BIGSERIAL PRIMARY KEY,
+    datestime TEXT,
+    floor INTEGER CHECK(floor IN (0, 1)),
+    ordertxt TEXT,
+    orderidx INTEGER CHECK(orderidx IN (0, 1, 2))
+);
+
+-- 부대별 최신 식단 (스냅샷의 jjam 테이블과 같은 열)
+CREATE TABLE menus(
+    id BIGSERIAL PRIMARY KEY,
+    unit TEXT NOT NULL,
+    dates TEXT NOT NULL,
+    brst TEXT,
+    brst_cal DOUBLE PRECISION,
+    lunc TEXT,
+    lunc_cal DOUBLE PRECISION,
+    dinr TEXT,
+    dinr_cal DOUBLE PRECISION,
+    adspcfd TEXT,
+    adspcfd_cal DOUBLE PRECISION,
+    sum_cal DOUBLE PRECISION
+);
+CREATE INDEX menus_unit_dates ON menus(unit, dates);
+
+CREATE TABLE meta(
+    key TEXT PRIMARY KEY,
+    value TEXT
+);
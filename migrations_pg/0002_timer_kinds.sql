@@ -0,0 +1,13 @@
+-- 예약 종류는 src/kinds.rs 의 표로 관리하므로 timer.what 의 CHECK 목록을 없앰
+
+ALTER TABLE timer DROP CONSTRAINT IF EXISTS timer_what_check;
+
+DELETE FROM timer WHERE what IS NULL;
+
+ALTER TABLE timer ALTER COLUMN what SET NOT NULL;
+
+-- 예전 코드는 아침메뉴를 breakfastorder 로, 아침식집을 breakfast 로 뒤바꿔 저장했으므로 바로잡음
+-- (userid, what) 이 UNIQUE 라서 둘 다 예약한 사용자가 있으면 한 번에 못 바꾸므로 임시 이름을 거침
+UPDATE timer SET what = 'breakfast.swap' WHERE what = 'breakfast';
+UPDATE timer SET what = 'breakfast' WHERE what = 'breakfastorder';
+UPDATE timer SET what = 'breakfastorder' WHERE what = 'breakfast.swap';
@@ -0,0 +1,12 @@
+-- menus 의 끼니 칸을 요리 단위로 나눈 것 (SQLite 쪽 dishes 와 같은 열)
+
+CREATE TABLE dishes(
+    id BIGSERIAL PRIMARY KEY,
+    unit TEXT NOT NULL,
+    dates TEXT NOT NULL,
+    meal TEXT NOT NULL,
+    name TEXT NOT NULL,
+    allergens TEXT,
+    kcal DOUBLE PRECISION
+);
+CREATE INDEX dishes_unit_dates ON dishes(unit, dates);
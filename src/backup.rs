@@ -0,0 +1,365 @@
+// data/ 의 SQLite 파일 백업과 복원
+//
+// 봇이 돌고 있는 중에 파일을 그냥 복사하면 WAL 이 반쯤 들어간 채로 복사될 수 있어서
+// VACUUM INTO 로 한 시점의 일관된 사본을 data/backup/<YYYYMMDD-HHMMSS>/ 에 만듦
+// 대상: jjambot.sqlite (DATABASE_URL 이 postgres:// 면 그쪽은 pg_dump 로 따로)
+//
+// 복원은 봇을 멈추고 명령줄로만 함: 사본이 integrity_check 를 통과해야 바꿔 넣고,
+// 바꾸기 전 파일은 data/backup/<시각>-before-restore/ 로 옮겨 둠 (바꿔 넣다 실패하면 되돌림)
+// (-before-restore 는 백업 목록과 JJAM_KEEP_BACKUPS 정리에서 빠지므로 필요 없어지면 직접 지움)
+// 예전 백업에 같이 들어 있는 jjam-*.sqlite 스냅샷은 더 이상 안 읽으므로 되돌리지 않음 (식단은 다음 수집 때 다시 받음)
+// 봇과 쓰기 명령(import, backup, poll)은 도는 동안 data/jjambot.lock 을 잡고 있어서, 그동안은 복원을 거부함
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use sqlx::{Row, SqlitePool};
+use tokio::time::sleep;
+
+use crate::schedule::Schedule;
+use crate::state::AppState;
+use crate::{db, time_now, ShowError};
+
+pub const BACKUP_DIR: &str = "data/backup";
+const MAIN_FILE: &str = "jjambot.sqlite";
+const BEFORE_RESTORE: &str = "-before-restore";
+const LOCK_FILE: &str = "data/jjambot.lock";
+// menus, dishes 테이블을 만드는 마이그레이션 (이보다 예전 백업에는 없어도 시작할 때 만들어짐)
+const MENUS_MIGRATION: i64 = 3;
+
+const JJAM_BACKUP_SCHEDULE: &str = "JJAM_BACKUP_SCHEDULE";
+const DEFAULT_BACKUP_SCHEDULE: &str = "30 3 * * *";
+const JJAM_KEEP_BACKUPS: &str = "JJAM_KEEP_BACKUPS";
+const DEFAULT_KEEP_BACKUPS: usize = 7;
+
+fn backup_schedule() -> Schedule {
+    let value = std::env::var(JJAM_BACKUP_SCHEDULE).unwrap_or(DEFAULT_BACKUP_SCHEDULE.to_string());
+    value.parse().unwrap_or_else(|e| {
+        eprintln!("invalid {}: {}, using {}", JJAM_BACKUP_SCHEDULE, e, DEFAULT_BACKUP_SCHEDULE);
+        DEFAULT_BACKUP_SCHEDULE.parse().unwrap()
+    })
+}
+
+// 백업 이름 목록 (오래된 것부터)
+pub fn list() -> Vec<String> {
+    list_in(BACKUP_DIR)
+}
+
+fn list_in(backup_dir: &str) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(backup_dir).into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+        .filter(|name| !name.ends_with(".tmp") && !name.ends_with(BEFORE_RESTORE))
+        .collect();
+    names.sort();
+    names
+}
+
+// 봇이 도는 동안 들고 있는 잠금, 프로세스가 끝나면 (죽어도) OS 가 풀어줌
+pub fn lock() -> Result<fs::File, ShowError> {
+    let file = fs::OpenOptions::new().create(true).truncate(false).write(true).open(LOCK_FILE)
+        .map_err(|e| format!("{}: {}", LOCK_FILE, e))?;
+    match file.try_lock() {
+        Ok(()) => Ok(file),
+        Err(fs::TryLockError::WouldBlock) => Err(format!("{}: 다른 jjambot 이 돌고 있음", LOCK_FILE).into()),
+        Err(fs::TryLockError::Error(e)) => Err(format!("{}: {}", LOCK_FILE, e).into()),
+    }
+}
+
+// 새 백업을 만들고 이름을 돌려줌
+pub async fn backup(state: &AppState) -> Result<String, ShowError> {
+    let name = time_now().format("%Y%m%d-%H%M%S").to_string();
+    let dir = format!("{}/{}", BACKUP_DIR, name);
+    if Path::new(&dir).exists() {
+        return Err(format!("{}: 이미 있는 백업", name).into());
+    }
+    // 다 만들어진 뒤에 이름을 바꿔서, 중간에 멈춘 백업이 목록에 끼지 않게 함
+    let tmp = format!("{}.tmp", dir);
+    let _ = fs::remove_dir_all(&tmp);
+    fs::create_dir_all(&tmp).map_err(|e| format!("{}: {}", tmp, e))?;
+
+    let result = sqlx::query("VACUUM INTO ?").bind(format!("{}/{}", tmp, MAIN_FILE)).execute(&state.db).await;
+    if let Err(e) = result {
+        let _ = fs::remove_dir_all(&tmp);
+        return Err(e.into());
+    }
+    fs::rename(&tmp, &dir).map_err(|e| format!("{}: {}", dir, e))?;
+    println!("Backup created: {}", dir);
+    Ok(name)
+}
+
+// JJAM_KEEP_BACKUPS 개만 남기고 오래된 백업을 지움
+pub fn prune() -> Result<(), ShowError> {
+    let keep = std::env::var(JJAM_KEEP_BACKUPS).ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_KEEP_BACKUPS);
+    prune_in(BACKUP_DIR, keep)
+}
+
+fn prune_in(backup_dir: &str, keep: usize) -> Result<(), ShowError> {
+    let names = list_in(backup_dir);
+    for name in names.iter().take(names.len().saturating_sub(keep.max(1))) {
+        fs::remove_dir_all(format!("{}/{}", backup_dir, name)).map_err(|e| format!("{}: {}", name, e))?;
+        println!("Backup pruned: {}", name);
+    }
+    Ok(())
+}
+
+// 관리 백업: 새로 만들고 목록을 보여줌
+pub async fn backup_message(state: &AppState) -> String {
+    let created = match backup(state).await {
+        Ok(name) => {
+            if let Err(e) = prune() {
+                eprintln!("backup_error: {e}");
+            }
+            format!("백업 완료: {}", name)
+        },
+        Err(e) => format!("ERROR: {}", e),
+    };
+    format!("{}\n\n<b>백업 목록</b> ({})\n{}", created, BACKUP_DIR, list().join("\n"))
+}
+
+// 사본이 멀쩡한지: integrity_check, 그리고 봇이 읽을 테이블이 있는지
+async fn validate(path: &str) -> Result<(), ShowError> {
+    let db = SqlitePool::connect(&format!("sqlite://{}?mode=ro", path)).await?;
+    let result = async {
+        let check: Vec<String> = sqlx::query("PRAGMA integrity_check")
+            .fetch_all(&db).await?
+            .iter()
+            .map(|row| row.get::<String, _>(0))
+            .collect();
+        if check != ["ok"] {
+            return Err(format!("{}: integrity_check 실패 ({})", path, check.join(", ")).into());
+        }
+        let has_table = |table: &'static str| {
+            let db = &db;
+            async move {
+                sqlx::query(&format!("SELECT COUNT(*) FROM {}", table)).fetch_one(db).await
+                    .map(|_| ())
+                    .map_err(|e| ShowError::from(format!("{}: {} 테이블 없음 ({})", path, table, e)))
+            }
+        };
+        has_table("_sqlx_migrations").await?;
+        let versions: Vec<i64> = sqlx::query("SELECT version FROM _sqlx_migrations").fetch_all(&db).await?
+            .iter()
+            .map(|row| row.get::<i64, _>(0))
+            .collect();
+        // 이 바이너리가 모르는 마이그레이션이 들어간 백업이면 시작할 때 실패하므로 막음
+        if let Some(version) = versions.iter().find(|v| !db::MIGRATOR.iter().any(|m| m.version == **v)) {
+            return Err(format!("{}: 모르는 마이그레이션 {}", path, version).into());
+        }
+        for table in ["users", "timer", "orders", "meta"] {
+            has_table(table).await?;
+        }
+        if versions.contains(&MENUS_MIGRATION) {
+            has_table("menus").await?;
+            has_table("dishes").await?;
+        }
+        Ok(())
+    }.await;
+    db.close().await;
+    result
+}
+
+// jjambot restore <백업 이름>, 봇을 멈춘 상태에서 실행, 옮겨 둔 원래 파일이 있는 곳을 돌려줌
+pub async fn restore(name: &str) -> Result<String, ShowError> {
+    // 봇이 파일을 열고 있는 채로 바꾸면 WAL 이 섞이므로 끝날 때까지 잠금을 쥐고 있음
+    let _lock = lock().map_err(|e| format!("{} (봇을 멈춘 뒤에 복원하세요)", e))?;
+    let dir = format!("{}/{}", BACKUP_DIR, name);
+    if name.is_empty() || name.contains('/') || !Path::new(&dir).is_dir() {
+        return Err(format!("{}: 없는 백업 (목록: {})", name, list().join(", ")).into());
+    }
+    let source = format!("{}/{}", dir, MAIN_FILE);
+    if !Path::new(&source).is_file() {
+        return Err(format!("{}: {} 없음", dir, MAIN_FILE).into());
+    }
+    validate(&source).await?;
+
+    // 같은 파일시스템 안에 복사본을 만들어 두고 rename 으로 바꿔 넣음
+    let staged = format!("data/{}.restore", MAIN_FILE);
+    fs::copy(&source, &staged).map_err(|e| format!("{}: {}", staged, e))?;
+    let before = format!("{}/{}{}", BACKUP_DIR, time_now().format("%Y%m%d-%H%M%S"), BEFORE_RESTORE);
+    if let Err(e) = fs::create_dir_all(&before) {
+        let _ = fs::remove_file(&staged);
+        return Err(format!("{}: {}", before, e).into());
+    }
+    swap_in(&staged, "data", &before)?;
+    Ok(before)
+}
+
+// data_dir 의 jjambot.sqlite 를 (남아 있는 -wal, -shm 과 함께) before 로 옮기고 staged 를 넣음
+// 중간에 실패하면 옮긴 파일을 되돌려서 원래대로 둠
+fn swap_in(staged: &str, data_dir: &str, before: &str) -> Result<(), ShowError> {
+    let mut moved: Vec<(String, String)> = Vec::new();
+    let result = 'swap: {
+        // 남아 있는 -wal 이 복원한 파일에 덮어써지지 않도록 같이 치움
+        for suffix in ["", "-wal", "-shm"] {
+            let current = format!("{}/{}{}", data_dir, MAIN_FILE, suffix);
+            if !Path::new(&current).exists() {
+                continue;
+            }
+            let saved = format!("{}/{}{}", before, MAIN_FILE, suffix);
+            if let Err(e) = fs::rename(&current, &saved) {
+                break 'swap Err(format!("{}: {}", current, e));
+            }
+            moved.push((current, saved));
+        }
+        let target = format!("{}/{}", data_dir, MAIN_FILE);
+        fs::rename(staged, &target).map_err(|e| format!("{}: {}", target, e))
+    };
+    let Err(e) = result else {
+        return Ok(());
+    };
+    for (current, saved) in moved.iter().rev() {
+        if let Err(e) = fs::rename(saved, current) {
+            eprintln!("restore_error: {} 을 되돌리지 못함 ({}), 직접 {} 로 옮기세요", saved, e, current);
+        }
+    }
+    let _ = fs::remove_file(staged);
+    Err(e.into())
+}
+
+// JJAM_BACKUP_SCHEDULE 시각마다 백업하고 오래된 것을 정리
+pub async fn backup_daemon(state: Arc<AppState>) -> Result<(), ShowError> {
+    let schedule = backup_schedule();
+    loop {
+        let now = time_now().naive_utc();
+        let Some(next) = schedule.next_after(now) else {
+            eprintln!("backup_error: no next backup time for {}", schedule);
+            return Ok(());
+        };
+        println!("next backup at {}", next.format("%Y-%m-%d %H:%M"));
+        sleep((next - now).to_std().unwrap_or_default()).await;
+
+        match backup(&state).await {
+            Ok(_) => if let Err(e) = prune() {
+                eprintln!("backup_error: {e}");
+            },
+            Err(e) => eprintln!("backup_error: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use sqlx::migrate::Migrator;
+    use sqlx::sqlite::SqliteConnectOptions;
+
+    use super::*;
+
+    // 테스트마다 따로 쓰는 빈 디렉터리
+    fn temp_dir(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("jjambot-backup-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.to_str().unwrap().to_string()
+    }
+
+    // 앞의 migrations 개 마이그레이션만 적용한 DB 파일
+    async fn migrated(path: &str, migrations: usize) {
+        let db = SqlitePool::connect_with(SqliteConnectOptions::new().filename(path).create_if_missing(true)).await.unwrap();
+        let migrator = Migrator {
+            migrations: Cow::Owned(db::MIGRATOR.migrations[..migrations].to_vec()),
+            ignore_missing: false,
+            locking: true,
+        };
+        migrator.run(&db).await.unwrap();
+        db.close().await;
+    }
+
+    #[tokio::test]
+    async fn validate_accepts_migrated_databases() {
+        let dir = temp_dir("valid");
+        let current = format!("{}/current.sqlite", dir);
+        migrated(&current, db::MIGRATOR.migrations.len()).await;
+        validate(&current).await.unwrap();
+        // menus 가 생기기 전 백업도 시작할 때 마이그레이션 되므로 받음
+        let old = format!("{}/old.sqlite", dir);
+        migrated(&old, (MENUS_MIGRATION - 1) as usize).await;
+        validate(&old).await.unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn validate_rejects_empty_corrupt_and_newer_databases() {
+        let dir = temp_dir("invalid");
+        let empty = format!("{}/empty.sqlite", dir);
+        fs::write(&empty, b"").unwrap();
+        let e = validate(&empty).await.unwrap_err();
+        assert!(e.to_string().contains("_sqlx_migrations 테이블 없음"), "{}", e);
+
+        let corrupt = format!("{}/corrupt.sqlite", dir);
+        fs::write(&corrupt, b"SQLite format 3\0 but not really a database file".repeat(100)).unwrap();
+        assert!(validate(&corrupt).await.is_err());
+
+        let missing = format!("{}/missing.sqlite", dir);
+        assert!(validate(&missing).await.is_err());
+
+        // menus 마이그레이션은 적용됐는데 테이블이 없으면 거부
+        let dropped = format!("{}/dropped.sqlite", dir);
+        migrated(&dropped, db::MIGRATOR.migrations.len()).await;
+        let db = SqlitePool::connect(&format!("sqlite://{}", dropped)).await.unwrap();
+        sqlx::query("DROP TABLE menus").execute(&db).await.unwrap();
+        sqlx::query("INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time) VALUES (9999, 'newer', 1, x'00', 0)")
+            .execute(&db).await.unwrap();
+        db.close().await;
+        let e = validate(&dropped).await.unwrap_err();
+        assert!(e.to_string().contains("모르는 마이그레이션 9999"), "{}", e);
+
+        let db = SqlitePool::connect(&format!("sqlite://{}", dropped)).await.unwrap();
+        sqlx::query("DELETE FROM _sqlx_migrations WHERE version=9999").execute(&db).await.unwrap();
+        db.close().await;
+        let e = validate(&dropped).await.unwrap_err();
+        assert!(e.to_string().contains("menus 테이블 없음"), "{}", e);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn prune_keeps_newest_and_skips_before_restore() {
+        let dir = temp_dir("prune");
+        for name in ["20240101-000000", "20240102-000000", "20240103-000000", "20240104-000000.tmp", "20240102-120000-before-restore"] {
+            fs::create_dir_all(format!("{}/{}", dir, name)).unwrap();
+        }
+        fs::write(format!("{}/20240105-000000", dir), b"not a backup").unwrap();
+        assert_eq!(list_in(&dir), ["20240101-000000", "20240102-000000", "20240103-000000"]);
+
+        prune_in(&dir, 2).unwrap();
+        assert_eq!(list_in(&dir), ["20240102-000000", "20240103-000000"]);
+        assert!(Path::new(&format!("{}/20240102-120000-before-restore", dir)).is_dir());
+        // 0 이어도 가장 최근 하나는 남김
+        prune_in(&dir, 0).unwrap();
+        assert_eq!(list_in(&dir), ["20240103-000000"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn swap_in_moves_current_files_and_rolls_back_on_error() {
+        let dir = temp_dir("swap");
+        let data = format!("{}/data", dir);
+        let before = format!("{}/before", dir);
+        fs::create_dir_all(&data).unwrap();
+        fs::create_dir_all(&before).unwrap();
+        fs::write(format!("{}/{}", data, MAIN_FILE), b"old").unwrap();
+        fs::write(format!("{}/{}-wal", data, MAIN_FILE), b"old wal").unwrap();
+
+        // 넣을 파일이 없으면 실패하고 원래 파일은 제자리에
+        let e = swap_in(&format!("{}/missing.restore", dir), &data, &before).unwrap_err();
+        assert!(e.to_string().contains(MAIN_FILE), "{}", e);
+        assert_eq!(fs::read(format!("{}/{}", data, MAIN_FILE)).unwrap(), b"old");
+        assert_eq!(fs::read(format!("{}/{}-wal", data, MAIN_FILE)).unwrap(), b"old wal");
+        assert_eq!(fs::read_dir(&before).unwrap().count(), 0);
+
+        let staged = format!("{}/new.restore", dir);
+        fs::write(&staged, b"new").unwrap();
+        swap_in(&staged, &data, &before).unwrap();
+        assert_eq!(fs::read(format!("{}/{}", data, MAIN_FILE)).unwrap(), b"new");
+        assert!(!Path::new(&format!("{}/{}-wal", data, MAIN_FILE)).exists());
+        assert_eq!(fs::read(format!("{}/{}", before, MAIN_FILE)).unwrap(), b"old");
+        assert_eq!(fs::read(format!("{}/{}-wal", before, MAIN_FILE)).unwrap(), b"old wal");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,127 @@
+// 통합 테스트용 국방부 OpenAPI 대역 서버
+//
+// MND_BASE_URL=http://127.0.0.1:8480 으로 jjambot 을 띄우면 실제 API 대신 이 서버를 씀
+// fixtures/<서비스명>.json 을 실제 응답과 같은 모양으로 두면 요청 위치에 맞춰 잘라서 돌려줌
+//
+// MOCK_MND_ADDR      : 바인드 주소 (기본 127.0.0.1:8480)
+// MOCK_MND_FIXTURES  : 픽스처 디렉터리 (기본 fixtures)
+// MOCK_MND_TOKEN     : 지정하면 다른 인증키는 INFO-100 으로 거부
+// MOCK_MND_QUOTA     : 지정한 횟수 이상 요청하면 ERROR-337
+// MOCK_MND_FAIL_EVERY: n번째 요청마다 HTTP 500 (재시도, 이어받기 확인용)
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const MAX_PAGE_SIZE: u64 = 1000;
+
+struct Config {
+    fixtures: String,
+    token: Option<String>,
+    quota: Option<u32>,
+    fail_every: Option<u32>,
+    requests: AtomicU32,
+}
+
+fn result(code: &str, message: &str) -> String {
+    serde_json::json!({ "RESULT": { "CODE": code, "MESSAGE": message } }).to_string()
+}
+
+fn respond(config: &Config, path: &str) -> (u16, String) {
+    let count = config.requests.fetch_add(1, Ordering::SeqCst) + 1;
+    if config.fail_every.is_some_and(|n| n > 0 && count.is_multiple_of(n)) {
+        return (500, "Internal Server Error".to_string());
+    }
+    if config.quota.is_some_and(|quota| count > quota) {
+        return (200, result("ERROR-337", "일별 트래픽 제한을 넘은 호출입니다."));
+    }
+
+    // /<인증키>/json/<서비스명>/<시작>/<끝>
+    let parts: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let [token, "json", service, start, end] = parts[..] else {
+        return (200, result("ERROR-300", "필수 값이 누락되어 있습니다."));
+    };
+    if config.token.as_deref().is_some_and(|t| t != token) {
+        return (200, result("INFO-100", "인증키가 유효하지 않습니다."));
+    }
+    let (Ok(start), Ok(end)) = (start.parse::<u64>(), end.parse::<u64>()) else {
+        return (200, result("ERROR-333", "요청위치 값의 타입이 유효하지 않습니다."));
+    };
+    if start == 0 || start > end {
+        return (200, result("ERROR-334", "요청종료위치 보다 요청시작위치가 더 큽니다."));
+    }
+    if end - start + 1 > MAX_PAGE_SIZE {
+        return (200, result("ERROR-336", "데이터요청은 한번에 최대 1000건을 넘을 수 없습니다."));
+    }
+
+    let Ok(fixture) = std::fs::read_to_string(format!("{}/{}.json", config.fixtures, service)) else {
+        return (200, result("ERROR-310", "해당하는 서비스를 찾을 수 없습니다."));
+    };
+    let mut fixture: serde_json::Value = match serde_json::from_str(&fixture) {
+        Ok(v) => v,
+        Err(e) => return (500, format!("broken fixture {}: {}", service, e)),
+    };
+    let rows = fixture[service]["row"].take();
+    let rows = rows.as_array().cloned().unwrap_or_default();
+    if start as usize > rows.len() {
+        return (200, result("INFO-200", "해당하는 데이터가 없습니다."));
+    }
+
+    let page = &rows[start as usize - 1..(end as usize).min(rows.len())];
+    (200, serde_json::json!({
+        service: {
+            "list_total_count": rows.len(),
+            "RESULT": { "CODE": "INFO-000", "MESSAGE": "정상 처리되었습니다." },
+            "row": page,
+        }
+    }).to_string())
+}
+
+async fn handle(mut stream: TcpStream, config: Arc<Config>) -> std::io::Result<()> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+
+    let request = String::from_utf8_lossy(&buf);
+    let path = request.split_whitespace().nth(1).unwrap_or("/");
+    let (status, body) = respond(&config, path.split('?').next().unwrap_or(path));
+    println!("{} {}", status, path);
+
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, if status == 200 { "OK" } else { "Internal Server Error" }, body.len(), body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let addr = std::env::var("MOCK_MND_ADDR").unwrap_or("127.0.0.1:8480".to_string());
+    let config = Arc::new(Config {
+        fixtures: std::env::var("MOCK_MND_FIXTURES").unwrap_or("fixtures".to_string()),
+        token: std::env::var("MOCK_MND_TOKEN").ok(),
+        quota: std::env::var("MOCK_MND_QUOTA").ok().and_then(|v| v.parse().ok()),
+        fail_every: std::env::var("MOCK_MND_FAIL_EVERY").ok().and_then(|v| v.parse().ok()),
+        requests: AtomicU32::new(0),
+    });
+
+    let listener = TcpListener::bind(&addr).await?;
+    println!("mock MND OpenAPI listening on http://{}", addr);
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let config = config.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle(stream, config).await {
+                eprintln!("mock_error: {e}");
+            }
+        });
+    }
+}
@@ -0,0 +1,734 @@
+// 텔레그램 명령
+//
+// 슬래시 명령(/lunch)은 BotCommands 로, 슬래시 없이 쓰는 한국어/영어 단어(점심, lunch)는 ALIASES 로
+// 같은 Command 가 됨. 슬래시 명령 목록은 setMyCommands 로 등록해서 텔레그램 입력창에 자동완성으로 뜸
+//
+// 명령마다 handler 하나: (상태, Invocation) -> Reply, 텔레그램으로 보내는 것은 send 가 맡음
+// 글자가 없는 메시지(사진, 스티커 등)나 빈 메시지는 Invocation 이 안 만들어져서 아무 handler 도 안 탐
+// 명령 이름은 대소문자를 안 가림 (/LUNCH, Lunch), 인자는 적은 그대로 넘김
+use std::sync::Arc;
+
+use chrono::{Datelike, Duration, NaiveTime};
+use teloxide::dispatching::UpdateHandler;
+use teloxide::types::{Me, ParseMode};
+use teloxide::utils::command::BotCommands;
+use teloxide::{dptree, prelude::*};
+
+use crate::dish::{self, allergen_name, allergen_numbers, diet_tag, parse_kcal};
+use crate::dates;
+use crate::state::AppState;
+use crate::storage::UserData;
+use crate::{
+    backup, dish_history, get_jjam, get_menus, kcal_meals, kinds, list_menus, list_quality, poll_status,
+    search_dishes, time_now, unit_codes, update_orderidx, user_profile, user_unit, MealType, ShowError, ORDERIDX, ORDERS,
+};
+
+#[derive(BotCommands, Clone, Copy, Debug, PartialEq)]
+#[command(rename_rule = "lowercase")]
+pub enum Command {
+    #[command(description = "시작하기")]
+    Start,
+    #[command(description = "도움말")]
+    Help,
+    #[command(description = "아침 메뉴, 날짜를 붙이면 그 날 (예: 아침 모레)")]
+    Breakfast,
+    #[command(description = "점심 메뉴, 날짜를 붙이면 그 날 (예: 점심 모레)")]
+    Lunch,
+    #[command(description = "저녁 메뉴, 날짜를 붙이면 그 날 (예: 저녁 모레)")]
+    Dinner,
+    #[command(description = "간식, 날짜를 붙이면 그 날 (예: 간식 12/25)")]
+    Snack,
+    #[command(description = "내일 아침 메뉴")]
+    Tomorrow,
+    #[command(description = "식사순서")]
+    Order,
+    #[command(description = "부대 확인, 변경")]
+    Unit,
+    #[command(description = "알림 예약")]
+    Reserve,
+    #[command(description = "알림 예약 취소")]
+    Delete,
+    #[command(description = "예약한 알림 목록")]
+    Reservations,
+    #[command(description = "알레르기 등록, 삭제")]
+    Allergy,
+    #[command(description = "피하는 음식 등록, 삭제")]
+    Avoid,
+    #[command(description = "요리가 언제 나오는지")]
+    When,
+    #[command(description = "요리 검색")]
+    Search,
+    #[command(description = "칼로리 이하 끼니 찾기")]
+    Kcal,
+    #[command(description = "식단 수집 상태")]
+    Status,
+    #[command(description = "현재 시각")]
+    Time,
+    // 관리자만 쓰므로 목록에는 안 올림
+    #[command(description = "off")]
+    Admin,
+}
+
+// 슬래시 없이 쓰는 이름, 첫 번째가 대표 (슬래시 명령의 사용법 안내에 씀)
+const ALIASES: &[(Command, &[&str])] = &[
+    (Command::Start, &["시작"]),
+    (Command::Help, &["도움말", "help"]),
+    (Command::Breakfast, &["아침", "아침메뉴", "아침식사"]),
+    (Command::Lunch, &["점심", "점심메뉴", "점심식사"]),
+    (Command::Dinner, &["저녁", "저녁메뉴", "저녁식사"]),
+    (Command::Snack, &["간식", "부식", "특식"]),
+    (Command::Tomorrow, &["내일아침", "낼아침", "내일아침메뉴", "내일아침식사"]),
+    (Command::Order, &["식사순서", "식집순서", "order"]),
+    (Command::Unit, &["부대", "부대코드", "unit"]),
+    (Command::Reserve, &["예약", "등록", "reserve"]),
+    (Command::Delete, &["삭제", "제거", "delete"]),
+    (Command::Reservations, &["예약목록", "알림목록", "reservations"]),
+    (Command::Allergy, &["알레르기", "알러지", "allergy"]),
+    (Command::Avoid, &["비선호", "식성", "avoid"]),
+    (Command::When, &["언제", "when"]),
+    (Command::Search, &["검색", "찾기", "search"]),
+    (Command::Kcal, &["칼로리", "kcal"]),
+    (Command::Status, &["상태", "status"]),
+    (Command::Time, &["시간", "time", "date", "datetime"]),
+    (Command::Admin, &["관리", "설정", "admin"]),
+];
+
+impl Command {
+    fn aliases(self) -> &'static [&'static str] {
+        ALIASES.iter().find(|(command, _)| *command == self).map(|(_, aliases)| *aliases).unwrap_or(&[])
+    }
+
+    fn takes_date(self) -> bool {
+        matches!(self, Command::Breakfast | Command::Lunch | Command::Dinner | Command::Snack)
+    }
+
+    fn from_alias(word: &str) -> Option<Command> {
+        ALIASES.iter().find(|(_, aliases)| aliases.contains(&word)).map(|(command, _)| *command)
+    }
+}
+
+// 메시지 하나를 명령으로 읽은 것
+#[derive(Clone, Debug)]
+pub struct Invocation {
+    pub command: Command,
+    pub user: UserData,
+    // 사용자가 쓴 명령 이름 (슬래시 명령이면 대표 이름), 식단 제목과 사용법 안내에 씀
+    pub word: String,
+    pub args: Vec<String>,
+}
+
+impl Invocation {
+    pub fn parse(text: &str, bot_username: &str, user: UserData) -> Option<Invocation> {
+        let mut words = text.split_whitespace();
+        let first = words.next()?.to_lowercase();
+        let (command, word) = if first.starts_with('/') {
+            let command = Command::parse(&first, bot_username).ok()?;
+            (command, command.aliases().first().map_or(first, |alias| alias.to_string()))
+        } else if let Some(command) = Command::from_alias(&first) {
+            (command, first)
+        } else {
+            return Invocation::dated(text, user);
+        };
+        Some(Invocation { command, user, word, args: words.map(str::to_string).collect() })
+    }
+
+    // 날짜를 앞에 적은 식단 조회 (모레 저녁, 다음주 월요일 아침): 날짜를 인자로 옮김
+    fn dated(text: &str, user: UserData) -> Option<Invocation> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let today = time_now().date_naive();
+        (1..words.len()).take(3).find_map(|i| {
+            let word = words[i].to_lowercase();
+            let command = Command::from_alias(&word).filter(|c| c.takes_date())?;
+            dates::parse(&words[..i], today)?;
+            let args = words[..i].iter().chain(&words[i + 1..]).map(|w| w.to_string()).collect();
+            Some(Invocation { command, user: user.clone(), word, args })
+        })
+    }
+
+    fn from_message(msg: Message, me: Me) -> Option<Invocation> {
+        let user = UserData {
+            userid: msg.chat.id.0,
+            username: msg.chat.username().unwrap_or("").to_string(),
+            realname: format!("{} {}", msg.chat.first_name().unwrap_or(""), msg.chat.last_name().unwrap_or("")),
+        };
+        Invocation::parse(msg.text()?, me.username(), user)
+    }
+
+    fn userid(&self) -> i64 {
+        self.user.userid
+    }
+}
+
+enum Reply {
+    Text(String),
+    Html(String),
+    // 답하지 않음 (관리자가 아닌 사람의 관리 명령 등)
+    Nothing,
+}
+
+async fn send(bot: &Bot, inv: &Invocation, reply: Result<Reply, ShowError>) -> ResponseResult<()> {
+    let chat = ChatId(inv.userid());
+    match reply {
+        Ok(Reply::Text(text)) => {
+            bot.send_message(chat, text).await?;
+        },
+        Ok(Reply::Html(text)) => {
+            bot.send_message(chat, text).parse_mode(ParseMode::Html).await?;
+        },
+        Ok(Reply::Nothing) => {},
+        Err(e) => eprintln!("daemon_error({:?}): {e}", inv.command),
+    }
+    Ok(())
+}
+
+macro_rules! handle {
+    ($command:ident, $handler:ident) => {
+        dptree::filter(|command: Command| command == Command::$command)
+            .endpoint(|bot: Bot, state: Arc<AppState>, inv: Invocation| async move {
+                send(&bot, &inv, $handler(&state, &inv).await).await
+            })
+    };
+}
+
+pub fn schema() -> UpdateHandler<teloxide::RequestError> {
+    Update::filter_message()
+        .inspect(|msg: Message| println!("{}", msg.chat.id))
+        .filter_map(Invocation::from_message)
+        .map(|inv: Invocation| inv.command)
+        .branch(handle!(Start, start))
+        .branch(handle!(Help, help))
+        .branch(handle!(Breakfast, breakfast))
+        .branch(handle!(Lunch, lunch))
+        .branch(handle!(Dinner, dinner))
+        .branch(handle!(Snack, snack))
+        .branch(handle!(Tomorrow, tomorrow))
+        .branch(handle!(Order, order))
+        .branch(handle!(Unit, unit))
+        .branch(handle!(Reserve, reserve))
+        .branch(handle!(Delete, delete))
+        .branch(handle!(Reservations, reservations))
+        .branch(handle!(Allergy, allergy))
+        .branch(handle!(Avoid, avoid))
+        .branch(handle!(When, when))
+        .branch(handle!(Search, search))
+        .branch(handle!(Kcal, kcal))
+        .branch(handle!(Status, status))
+        .branch(handle!(Time, time))
+        .branch(handle!(Admin, admin))
+}
+
+async fn start(state: &AppState, inv: &Invocation) -> Result<Reply, ShowError> {
+    let admin = inv.args == ["admin", "true"];
+    let _ = state.storage.add_user(&inv.user, admin).await;
+    Ok(Reply::Text("환영합니다! /help 로 도움말을 확인하세요!".to_string()))
+}
+
+fn help_text() -> String {
+    let lines: Vec<String> = Command::bot_commands().iter()
+        .filter_map(|bc| {
+            let command = Command::parse(&bc.command, "").ok()?;
+            Some(format!("{} ({}) - {}", bc.command, command.aliases().join(", "), bc.description))
+        })
+        .collect();
+    format!("명령 목록\n{}\n\n괄호 안의 말은 슬래시 없이 그대로 써도 됩니다.", lines.join("\n"))
+}
+
+async fn help(_state: &AppState, _inv: &Invocation) -> Result<Reply, ShowError> {
+    Ok(Reply::Text(help_text()))
+}
+
+// 날짜를 안 적으면 오늘에서 days 일 뒤 (점심 모레, 저녁 12/25 처럼 적으면 그 날)
+async fn menu(state: &AppState, inv: &Invocation, meal: MealType, days: i64) -> Result<Reply, ShowError> {
+    let store = state.storage.as_ref();
+    let today = time_now().date_naive();
+    let date = if inv.args.is_empty() {
+        today + Duration::days(days)
+    } else {
+        let args: Vec<&str> = inv.args.iter().map(String::as_str).collect();
+        let Some(date) = dates::parse(&args, today) else {
+            let w = &inv.word;
+            return Ok(Reply::Text(format!(
+                "날짜를 알 수 없습니다: {}\n예: {} 12/25, {} 모레, {} 다음주 월요일, {} 3일뒤",
+                inv.args.join(" "), w, w, w, w
+            )));
+        };
+        date
+    };
+    let unit = user_unit(inv.userid(), store).await;
+    let jjams = get_jjam(state, &unit, date).await.unwrap_or_else(|e| {
+        eprintln!("daemon_error: {e}");
+        Vec::new()
+    });
+    // 빈 식단을 보여주는 대신 받아 둔 범위를 알려줌 (범위 안이어도 그 날만 빠져 있을 수 있음)
+    if jjams.is_empty() {
+        let Some(stored) = store.menu_range(&unit).await? else {
+            return Ok(Reply::Text("아직 받아 둔 식단이 없습니다.".to_string()));
+        };
+        let range = format!("{} ~ {}", stored.first.format("%Y-%m-%d"), stored.last.format("%Y-%m-%d"));
+        return Ok(Reply::Text(if date < stored.first || date > stored.last {
+            format!("{} 식단은 없습니다.\n조회할 수 있는 날짜: {}", dates::label(date), range)
+        } else {
+            format!("{} 식단은 없습니다.\n받아 둔 식단({})에 이 날짜가 빠져 있습니다.", dates::label(date), range)
+        }));
+    }
+    let title = if date == today { inv.word.clone() } else { format!("{} {}", dates::label(date), inv.word) };
+    Ok(Reply::Html(get_menus(&jjams, &title, meal, &user_profile(inv.userid(), store).await).await?))
+}
+
+async fn breakfast(state: &AppState, inv: &Invocation) -> Result<Reply, ShowError> {
+    menu(state, inv, MealType::Breakfast, 0).await
+}
+
+async fn lunch(state: &AppState, inv: &Invocation) -> Result<Reply, ShowError> {
+    menu(state, inv, MealType::Lunch, 0).await
+}
+
+async fn dinner(state: &AppState, inv: &Invocation) -> Result<Reply, ShowError> {
+    menu(state, inv, MealType::Dinner, 0).await
+}
+
+async fn snack(state: &AppState, inv: &Invocation) -> Result<Reply, ShowError> {
+    menu(state, inv, MealType::Snack, 0).await
+}
+
+async fn tomorrow(state: &AppState, inv: &Invocation) -> Result<Reply, ShowError> {
+    menu(state, inv, MealType::Breakfast, 1).await
+}
+
+async fn order(_state: &AppState, _inv: &Invocation) -> Result<Reply, ShowError> {
+    let mut floor = 1;
+    let now = time_now();
+    if now.weekday() as u32 <= chrono::Weekday::Fri as u32 && (now.time() > NaiveTime::from_hms_opt(8, 30, 0).unwrap() && now.time() < NaiveTime::from_hms_opt(12, 0, 0).unwrap()) {
+        floor = 0;
+    }
+    Ok(Reply::Html(format!("<b>식집순서</b>: {}", ORDERS[(*ORDERIDX.lock().unwrap())[floor]])))
+}
+
+async fn unit(state: &AppState, inv: &Invocation) -> Result<Reply, ShowError> {
+    let store = state.storage.as_ref();
+    let units = unit_codes();
+    let Some(code) = inv.args.first() else {
+        return Ok(Reply::Text(format!(
+            "현재 부대: {}\n선택 가능한 부대: {}\n사용법: {} <부대코드>",
+            user_unit(inv.userid(), store).await, units.join(", "), inv.word
+        )));
+    };
+    if !units.contains(code) {
+        return Ok(Reply::Text(format!("{}는 등록되지 않은 부대코드입니다.\n선택 가능한 부대: {}", code, units.join(", "))));
+    }
+    store.set_user_unit(inv.userid(), code).await?;
+    Ok(Reply::Text(format!("부대가 {}(으)로 설정되었습니다.", code)))
+}
+
+async fn reserve(state: &AppState, inv: &Invocation) -> Result<Reply, ShowError> {
+    'errorjmp:{
+        let Some(name) = inv.args.first() else {
+            break 'errorjmp;
+        };
+        let Some(kind) = kinds::find(name) else {
+            break 'errorjmp;
+        };
+        let when = match inv.args.get(1) {
+            Some(w) => match NaiveTime::parse_from_str(w.get(..5).unwrap_or(w), "%H:%M") {
+                Ok(t) => t.format("%H:%M").to_string(),
+                Err(_) => break 'errorjmp,
+            },
+            None => kind.default_time.to_string(),
+        };
+        state.storage.add_book(inv.userid(), &when, kind.what).await?;
+        return Ok(Reply::Text(format!("{}에 {} 예약이 완료되었습니다.", when, name)));
+    }
+    Ok(Reply::Text(format!(r#"
+사용법은 다음과 같습니다.
+{} <종류> <시간(생략가능)>
+{} 점심메뉴 11:00
+<종류> 목록: {}
+<시간> 입력시 시:분 형태로 입력해주십시오.
+    "#, inv.word, inv.word, kinds::names())))
+}
+
+async fn delete(state: &AppState, inv: &Invocation) -> Result<Reply, ShowError> {
+    'errorjmp:{
+        let Some(name) = inv.args.first() else {
+            break 'errorjmp;
+        };
+        let Some(kind) = kinds::find(name) else {
+            break 'errorjmp;
+        };
+        if state.storage.del_book(inv.userid(), kind.what).await.is_err() {
+            break 'errorjmp;
+        }
+        return Ok(Reply::Text(format!("{} 예약이 취소되었습니다.", name)));
+    }
+    Ok(Reply::Text(format!(r#"
+사용법은 다음과 같습니다.
+{} <종류>
+{} 점심메뉴
+<종류> 목록: {}
+    "#, inv.word, inv.word, kinds::names())))
+}
+
+async fn reservations(state: &AppState, inv: &Invocation) -> Result<Reply, ShowError> {
+    let books = state.storage.books_of(inv.userid()).await?;
+    let lines: Vec<String> = books.iter()
+        .map(|(what, when)| format!("{} {}", when, kinds::by_what(what).map(kinds::Kind::name).unwrap_or(what)))
+        .collect();
+    Ok(Reply::Html(if lines.is_empty() {
+        format!("예약된 알림이 없습니다.\n예약 &lt;종류&gt; &lt;시간(생략가능)&gt;\n&lt;종류&gt; 목록: {}", kinds::names())
+    } else {
+        format!("<b>예약된 알림</b>\n{}", lines.join("\n"))
+    }))
+}
+
+async fn allergy(state: &AppState, inv: &Invocation) -> Result<Reply, ShowError> {
+    let store = state.storage.as_ref();
+    let args = &inv.args;
+    let mut unknown: Vec<&str> = Vec::new();
+    if args.first().is_some_and(|w| w == "삭제" || w == "delete") {
+        // 알레르기 삭제 [항목...], 항목이 없으면 전부 삭제
+        if args.len() == 1 {
+            store.del_allergy(inv.userid(), None).await?;
+        }
+        for number in args[1..].iter().flat_map(|w| allergen_numbers(w)) {
+            store.del_allergy(inv.userid(), Some(number)).await?;
+        }
+    } else {
+        for word in args {
+            let numbers = allergen_numbers(word);
+            if numbers.is_empty() {
+                unknown.push(word);
+            }
+            for number in numbers {
+                store.add_allergy(inv.userid(), number).await?;
+            }
+        }
+    }
+
+    let registered: Vec<String> = user_profile(inv.userid(), store).await.allergies.iter()
+        .map(|a| format!("{}({})", allergen_name(*a), a))
+        .collect();
+    let word = &inv.word;
+    Ok(Reply::Text(format!(
+        "{}등록된 알레르기: {}\n\n사용법\n{} <항목...> : 등록 (예: {} 우유 땅콩)\n{} 삭제 <항목...> : 삭제 (항목 생략시 전부)\n항목: {}",
+        if unknown.is_empty() { String::new() } else { format!("알 수 없는 항목: {}\n\n", unknown.join(", ")) },
+        if registered.is_empty() { "없음".to_string() } else { registered.join(", ") },
+        word, word, word,
+        dish::ALLERGENS.iter().map(|(_, name, _)| *name).collect::<Vec<_>>().join(", ")
+    )))
+}
+
+async fn avoid(state: &AppState, inv: &Invocation) -> Result<Reply, ShowError> {
+    let store = state.storage.as_ref();
+    // 비선호 <항목...> [오늘], 오늘을 붙이면 오늘 하루만 적용
+    let today_only = inv.args.last().is_some_and(|w| w == "오늘");
+    let args: Vec<&str> = inv.args.iter().map(String::as_str).filter(|w| *w != "오늘").collect();
+    let mut unknown: Vec<&str> = Vec::new();
+    if args.first().is_some_and(|w| *w == "삭제" || *w == "delete") {
+        if args.len() == 1 {
+            store.del_avoid(inv.userid(), None).await?;
+        }
+        for tag in args[1..].iter().filter_map(|w| diet_tag(w)) {
+            store.del_avoid(inv.userid(), Some(tag)).await?;
+        }
+    } else {
+        let until = today_only.then(|| time_now().date_naive().format("%Y-%m-%d").to_string());
+        for word in &args {
+            let Some(tag) = diet_tag(word) else {
+                unknown.push(word);
+                continue;
+            };
+            store.add_avoid(inv.userid(), tag, until.as_deref()).await?;
+        }
+    }
+
+    let registered = user_profile(inv.userid(), store).await.avoids;
+    let word = &inv.word;
+    Ok(Reply::Text(format!(
+        "{}피하는 음식: {}\n\n사용법\n{} <항목...> : 등록 (예: {} 돼지고기 해산물)\n{} 채식 오늘 : 오늘 하루만 적용\n{} 삭제 <항목...> : 삭제 (항목 생략시 전부)\n항목: {}",
+        if unknown.is_empty() { String::new() } else { format!("알 수 없는 항목: {}\n\n", unknown.join(", ")) },
+        if registered.is_empty() { "없음".to_string() } else { registered.join(", ") },
+        word, word, word, word,
+        dish::DIET_TAGS.iter().map(|tag| tag.name).collect::<Vec<_>>().join(", ")
+    )))
+}
+
+async fn when(state: &AppState, inv: &Invocation) -> Result<Reply, ShowError> {
+    if inv.args.is_empty() {
+        return Ok(Reply::Text(format!("사용법: {} <요리 이름>\n예: {} 치킨", inv.word, inv.word)));
+    }
+    let unit = user_unit(inv.userid(), state.storage.as_ref()).await;
+    Ok(Reply::Html(dish_history(state, &unit, &inv.args.join(" ")).await.unwrap_or_else(|e| format!("ERROR: {}", e))))
+}
+
+async fn search(state: &AppState, inv: &Invocation) -> Result<Reply, ShowError> {
+    if inv.args.is_empty() {
+        return Ok(Reply::Text(format!("사용법: {} <요리 이름>\n예: {} 치킨", inv.word, inv.word)));
+    }
+    let unit = user_unit(inv.userid(), state.storage.as_ref()).await;
+    Ok(Reply::Html(search_dishes(state, &unit, &inv.args.join(" ")).await.unwrap_or_else(|e| format!("ERROR: {}", e))))
+}
+
+async fn kcal(state: &AppState, inv: &Invocation) -> Result<Reply, ShowError> {
+    let Some(limit) = inv.args.first().and_then(|w| parse_kcal(w)) else {
+        return Ok(Reply::Text(format!("사용법: {} <칼로리>\n예: {} 700", inv.word, inv.word)));
+    };
+    let unit = user_unit(inv.userid(), state.storage.as_ref()).await;
+    Ok(Reply::Html(kcal_meals(state, &unit, limit).await.unwrap_or_else(|e| format!("ERROR: {}", e))))
+}
+
+async fn status(_state: &AppState, _inv: &Invocation) -> Result<Reply, ShowError> {
+    Ok(Reply::Html(poll_status()))
+}
+
+async fn time(_state: &AppState, _inv: &Invocation) -> Result<Reply, ShowError> {
+    Ok(Reply::Text(time_now().format("%Y-%m-%d %H:%M:%S").to_string()))
+}
+
+async fn admin(state: &AppState, inv: &Invocation) -> Result<Reply, ShowError> {
+    if !state.storage.is_admin(inv.userid()).await.unwrap_or(false) {
+        return Ok(Reply::Nothing);
+    }
+    let args = &inv.args;
+    let Some(sub) = args.first() else {
+        return Ok(Reply::Text("ERROR".to_string()));
+    };
+    let mut reply = String::new();
+    'adm_error:{
+        match sub.as_str() {
+            "changeorder"|"식사순서변경"|"식집순서변경"|"식사순서"|"식집순서" => {
+                if args.len() < 3 {
+                    reply = "This needs 2 more argumemts (floor, changes)".to_string();
+                    break 'adm_error;
+                }
+                let floor = match args[1].parse::<u32>() {
+                    Ok(floor) if floor >= 1 => (floor - 1) as usize,
+                    _ => break 'adm_error,
+                };
+                let Ok(change) = args[2].parse::<i32>() else {
+                    break 'adm_error;
+                };
+                return Ok(Reply::Text(format!("Now {}", ORDERS[update_orderidx(floor, change)])));
+            },
+            "quality"|"품질"|"검사" => {
+                return Ok(Reply::Html(list_quality(state).await.unwrap_or_else(|e| format!("ERROR: {}", e))));
+            },
+            "backup"|"백업" => {
+                return Ok(Reply::Html(backup::backup_message(state).await));
+            },
+            "menus"|"식단"|"snapshots"|"스냅샷" => {
+                return Ok(Reply::Html(list_menus(state).await.unwrap_or_else(|e| format!("ERROR: {}", e))));
+            },
+            _ => {},
+        }
+    }
+    let idx = [update_orderidx(0, 0), update_orderidx(1, 0)];
+    Ok(Reply::Text(format!("ERROR: {} \n사용법: {} {} <층수> <차이>\n1층: {}\n2층: {}", reply, inv.word, sub, ORDERS[idx[0]], ORDERS[idx[1]])))
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryStorage;
+    use crate::state::Config;
+    use crate::{default_unit, JjamRow};
+
+    async fn state() -> AppState {
+        AppState::with_storage(Box::new(MemoryStorage::new()), Config::default()).await.unwrap()
+    }
+
+    // 기본 부대(UNIT_CODE 첫 번째)에 오늘 점심 식단을 넣어 둔 상태
+    async fn state_with_lunch(dishes: &[(&str, f64)]) -> AppState {
+        let today = time_now().date_naive().format("%Y-%m-%d").to_string();
+        let storage = MemoryStorage::new();
+        storage.put_menus(&default_unit(), dishes.iter().map(|(name, kcal)| JjamRow {
+            dates: today.clone(), lunc: name.to_string(), lunc_cal: Some(*kcal), ..Default::default()
+        }).collect());
+        AppState::with_storage(Box::new(storage), Config::default()).await.unwrap()
+    }
+
+    fn user(userid: i64) -> UserData {
+        UserData { userid, ..Default::default() }
+    }
+
+    fn invoke(text: &str, userid: i64) -> Invocation {
+        Invocation::parse(text, "jjambot", user(userid)).unwrap()
+    }
+
+    fn text(reply: Reply) -> String {
+        match reply {
+            Reply::Text(text) | Reply::Html(text) => text,
+            Reply::Nothing => String::new(),
+        }
+    }
+
+    fn me() -> Me {
+        serde_json::from_value(serde_json::json!({
+            "id": 1, "is_bot": true, "first_name": "jjambot", "username": "jjambot",
+            "can_join_groups": false, "can_read_all_group_messages": false, "supports_inline_queries": false,
+        })).unwrap()
+    }
+
+    fn message(content: serde_json::Value) -> Message {
+        let mut msg = serde_json::json!({
+            "message_id": 1, "date": 0,
+            "chat": { "id": 7, "type": "private", "first_name": "홍", "last_name": "길동", "username": "hong" },
+        });
+        msg.as_object_mut().unwrap().extend(content.as_object().unwrap().clone());
+        serde_json::from_value(msg).unwrap()
+    }
+
+    #[test]
+    fn parse_resolves_aliases_and_slash_commands() {
+        let cases: &[(&str, Command, &str, &[&str])] = &[
+            ("점심", Command::Lunch, "점심", &[]),
+            ("점심식사 모레", Command::Lunch, "점심식사", &["모레"]),
+            ("/lunch", Command::Lunch, "점심", &[]),
+            ("/lunch@jjambot 12/25", Command::Lunch, "점심", &["12/25"]),
+            ("help", Command::Help, "help", &[]),
+            ("검색 치킨", Command::Search, "검색", &["치킨"]),
+            // 날짜를 앞에 적으면 인자로 옮김
+            ("모레 저녁", Command::Dinner, "저녁", &["모레"]),
+            ("다음주 월요일 아침", Command::Breakfast, "아침", &["다음주", "월요일"]),
+            // 명령 이름만 소문자로, 인자는 그대로
+            ("/LUNCH@JJAMBOT", Command::Lunch, "점심", &[]),
+            ("HELP", Command::Help, "help", &[]),
+            ("Search Chicken", Command::Search, "search", &["Chicken"]),
+        ];
+        for (text, command, word, args) in cases {
+            let inv = Invocation::parse(text, "jjambot", user(1)).unwrap_or_else(|| panic!("{}", text));
+            assert_eq!((inv.command, inv.word.as_str()), (*command, *word), "{}", text);
+            assert_eq!(inv.args, *args, "{}", text);
+        }
+    }
+
+    #[test]
+    fn parse_ignores_other_text() {
+        for text in ["", "   ", "안녕하세요", "/lunch@otherbot", "/없는명령", "모레 안녕"] {
+            assert!(Invocation::parse(text, "jjambot", user(1)).is_none(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn from_message_reads_text_and_ignores_the_rest() {
+        let inv = Invocation::from_message(message(serde_json::json!({ "text": "/lunch" })), me()).unwrap();
+        assert_eq!(inv.command, Command::Lunch);
+        assert_eq!((inv.user.userid, inv.user.username.as_str(), inv.user.realname.as_str()), (7, "hong", "홍 길동"));
+
+        let location = serde_json::json!({ "location": { "latitude": 37.5, "longitude": 127.0 } });
+        assert!(Invocation::from_message(message(location), me()).is_none());
+        let sticker = serde_json::json!({ "sticker": {
+            "file_id": "a", "file_unique_id": "b", "width": 512, "height": 512,
+            "type": "regular", "is_animated": false, "is_video": false,
+        } });
+        assert!(Invocation::from_message(message(sticker), me()).is_none());
+    }
+
+    #[tokio::test]
+    async fn lunch_shows_todays_menu() {
+        let state = state_with_lunch(&[("짜장밥(5.6)", 500.0), ("단무지", 10.0)]).await;
+        let reply = text(lunch(&state, &invoke("점심", 1)).await.unwrap());
+        assert!(reply.starts_with("<b>점심</b>"), "{}", reply);
+        assert!(reply.contains("짜장밥") && reply.contains("단무지"), "{}", reply);
+    }
+
+    #[tokio::test]
+    async fn menu_explains_dates_without_menu() {
+        let empty = state().await;
+        let today = time_now().date_naive();
+        let day = |days: i64| (today + Duration::days(days)).format("%Y-%m-%d").to_string();
+        let storage = MemoryStorage::new();
+        storage.put_menus(&default_unit(), [0, 2].iter().map(|days| JjamRow {
+            dates: day(*days), lunc: "비빔밥".to_string(), ..Default::default()
+        }).collect());
+        let state = AppState::with_storage(Box::new(storage), Config::default()).await.unwrap();
+        let range = format!("{} ~ {}", day(0), day(2));
+
+        let reply = text(lunch(&state, &invoke("모레 점심", 1)).await.unwrap());
+        assert!(reply.contains("비빔밥"), "{}", reply);
+        for (command, days) in [("점심 3일뒤", 3), ("어제 점심", -1), ("점심 10일뒤", 10)] {
+            let reply = text(lunch(&state, &invoke(command, 1)).await.unwrap());
+            let label = dates::label(today + Duration::days(days));
+            assert_eq!(reply, format!("{} 식단은 없습니다.\n조회할 수 있는 날짜: {}", label, range), "{}", command);
+        }
+        let reply = text(lunch(&state, &invoke("내일 점심", 1)).await.unwrap());
+        assert_eq!(reply, format!("{} 식단은 없습니다.\n받아 둔 식단({})에 이 날짜가 빠져 있습니다.", dates::label(today + Duration::days(1)), range));
+        assert!(text(lunch(&state, &invoke("점심 내일모래", 1)).await.unwrap()).starts_with("날짜를 알 수 없습니다: 내일모래"));
+
+        let reply = text(dinner(&empty, &invoke("저녁 모레", 1)).await.unwrap());
+        assert_eq!(reply, "아직 받아 둔 식단이 없습니다.");
+    }
+
+    #[tokio::test]
+    async fn search_and_kcal_read_stored_dishes() {
+        let state = state_with_lunch(&[("짜장밥(5.6)", 500.0), ("단무지", 10.0)]).await;
+        let reply = text(search(&state, &invoke("검색 짜장", 1)).await.unwrap());
+        assert!(reply.contains("점심 짜장밥"), "{}", reply);
+        assert!(!reply.contains("단무지"), "{}", reply);
+        assert!(text(search(&state, &invoke("검색 치킨", 1)).await.unwrap()).contains("앞으로의 식단에 없습니다."));
+
+        assert!(text(kcal(&state, &invoke("칼로리 600", 1)).await.unwrap()).contains("점심 510kcal"));
+        assert!(text(kcal(&state, &invoke("칼로리 500", 1)).await.unwrap()).contains("앞으로의 식단에 없습니다."));
+        assert!(text(kcal(&state, &invoke("칼로리", 1)).await.unwrap()).starts_with("사용법"));
+    }
+
+    #[tokio::test]
+    async fn allergy_adds_and_deletes() {
+        let state = state().await;
+        let reply = text(allergy(&state, &invoke("알레르기 우유 외계인", 1)).await.unwrap());
+        assert!(reply.starts_with("알 수 없는 항목: 외계인"), "{}", reply);
+        assert!(reply.contains("등록된 알레르기: 우유(2)"), "{}", reply);
+        assert_eq!(user_profile(1, state.storage.as_ref()).await.allergies, [2]);
+
+        let reply = text(allergy(&state, &invoke("알레르기 삭제", 1)).await.unwrap());
+        assert!(reply.starts_with("등록된 알레르기: 없음"), "{}", reply);
+    }
+
+    #[tokio::test]
+    async fn unit_rejects_unknown_code() {
+        let state = state().await;
+        let reply = text(unit(&state, &invoke("부대 없는부대", 1)).await.unwrap());
+        assert!(reply.starts_with("없는부대는 등록되지 않은 부대코드입니다."), "{}", reply);
+        assert_eq!(state.storage.user_unit(1).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn reserve_and_delete() {
+        let state = state().await;
+        let reply = text(reserve(&state, &invoke("예약 점심메뉴 11:30", 1)).await.unwrap());
+        assert_eq!(reply, "11:30에 점심메뉴 예약이 완료되었습니다.");
+        // 시각을 안 적으면 종류의 기본 시각, 같은 종류는 시각만 바뀜
+        reserve(&state, &invoke("/reserve 아침메뉴", 1)).await.unwrap();
+        reserve(&state, &invoke("예약 점심식사 11:45:00", 1)).await.unwrap();
+        assert_eq!(state.storage.books_of(1).await.unwrap(), [
+            ("breakfast".to_string(), "07:15".to_string()),
+            ("lunch".to_string(), "11:45".to_string()),
+        ]);
+        assert!(text(reservations(&state, &invoke("예약목록", 1)).await.unwrap()).contains("11:45 점심메뉴"));
+
+        let reply = text(delete(&state, &invoke("삭제 점심메뉴", 1)).await.unwrap());
+        assert_eq!(reply, "점심메뉴 예약이 취소되었습니다.");
+        assert_eq!(state.storage.books_of(1).await.unwrap(), [("breakfast".to_string(), "07:15".to_string())]);
+        assert!(state.storage.books_of(2).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn reserve_rejects_unknown_kind_and_time() {
+        let state = state().await;
+        for command in ["예약", "예약 브런치", "예약 점심메뉴 25:00", "예약 점심메뉴 점심"] {
+            let reply = text(reserve(&state, &invoke(command, 1)).await.unwrap());
+            assert!(reply.contains("사용법"), "{}: {}", command, reply);
+        }
+        assert!(text(delete(&state, &invoke("삭제 브런치", 1)).await.unwrap()).contains("사용법"));
+        assert!(state.storage.books_of(1).await.unwrap().is_empty());
+        assert!(text(reservations(&state, &invoke("예약목록", 1)).await.unwrap()).starts_with("예약된 알림이 없습니다."));
+    }
+
+    #[tokio::test]
+    async fn order_replies_current_order() {
+        let state = state().await;
+        let reply = text(order(&state, &invoke("식사순서", 1)).await.unwrap());
+        assert!(ORDERS.iter().any(|o| reply == format!("<b>식집순서</b>: {}", o)), "{}", reply);
+    }
+}
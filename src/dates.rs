@@ -0,0 +1,158 @@
+// 식단 조회에 쓰는 한국어 날짜
+//
+// 오늘, 내일(낼), 모레(내일모레), 글피, 어제, 그제(그저께)
+// 3일뒤, 3일 후, 2일전
+// 12/25, 12.25, 12-25, 12월25일, 12월 25일, 2024-12-25 (연도를 안 적으면 오늘에서 가까운 해)
+// 월요일, 이번주 월요일, 다음주 월요일, 다다음주 월요일, 지난주 월요일 (한 주는 월요일부터)
+// 띄어쓰기는 상관없음: 단어들을 붙여서 읽음
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+const WEEKDAYS: [(&str, Weekday); 7] = [
+    ("월", Weekday::Mon),
+    ("화", Weekday::Tue),
+    ("수", Weekday::Wed),
+    ("목", Weekday::Thu),
+    ("금", Weekday::Fri),
+    ("토", Weekday::Sat),
+    ("일", Weekday::Sun),
+];
+
+// 읽을 수 없거나 없는 날짜(2/30 등)면 None
+pub fn parse(words: &[&str], today: NaiveDate) -> Option<NaiveDate> {
+    let text: String = words.concat();
+    if text.is_empty() {
+        return None;
+    }
+    let days = match text.as_str() {
+        "오늘" => Some(0),
+        "내일" | "낼" => Some(1),
+        "모레" | "내일모레" | "낼모레" => Some(2),
+        "글피" => Some(3),
+        "어제" => Some(-1),
+        "그제" | "그저께" => Some(-2),
+        _ => None,
+    };
+    if let Some(days) = days {
+        return today.checked_add_signed(Duration::days(days));
+    }
+    relative(&text, today)
+        .or_else(|| weekday(&text, today))
+        .or_else(|| absolute(&text, today))
+}
+
+// N일뒤, N일후, N일전
+fn relative(text: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let (number, sign) = if let Some(n) = text.strip_suffix("일뒤").or_else(|| text.strip_suffix("일후")) {
+        (n, 1)
+    } else {
+        (text.strip_suffix("일전")?, -1)
+    };
+    let days: i64 = number.parse::<u16>().ok()?.into();
+    today.checked_add_signed(Duration::days(sign * days))
+}
+
+// (이번주|다음주|다다음주|지난주) X요일, 주를 안 적으면 오늘부터 처음 오는 X요일
+fn weekday(text: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let (weeks, rest) = [("이번주", 0), ("다음주", 1), ("다다음주", 2), ("지난주", -1), ("저번주", -1)].iter()
+        .find_map(|(prefix, weeks)| text.strip_prefix(prefix).map(|rest| (Some(*weeks), rest)))
+        .unwrap_or((None, text));
+    let name = rest.strip_suffix("요일").or(weeks.map(|_| rest))?;
+    let (_, day) = WEEKDAYS.iter().find(|(n, _)| *n == name)?;
+    let offset = match weeks {
+        Some(weeks) => weeks * 7 + day.num_days_from_monday() as i64 - today.weekday().num_days_from_monday() as i64,
+        None => (day.num_days_from_monday() as i64 - today.weekday().num_days_from_monday() as i64).rem_euclid(7),
+    };
+    today.checked_add_signed(Duration::days(offset))
+}
+
+// 12/25, 12월25일, 2024-12-25
+fn absolute(text: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let parts: Vec<&str> = match text.strip_suffix('일').and_then(|t| t.split_once('월')) {
+        Some((month, day)) => vec![month, day],
+        None => text.split(['/', '.', '-']).collect(),
+    };
+    let numbers: Vec<u32> = parts.iter().map(|p| p.parse().ok()).collect::<Option<_>>()?;
+    match numbers[..] {
+        [year, month, day] => NaiveDate::from_ymd_opt(year as i32, month, day),
+        [month, day] => (today.year() - 1..=today.year() + 1)
+            .filter_map(|year| NaiveDate::from_ymd_opt(year, month, day))
+            .min_by_key(|date| (*date - today).num_days().abs()),
+        _ => None,
+    }
+}
+
+// 답장 제목에 붙이는 날짜 (예: 12/25(수))
+pub fn label(date: NaiveDate) -> String {
+    let (name, _) = WEEKDAYS[date.weekday().num_days_from_monday() as usize];
+    format!("{}({})", date.format("%m/%d"), name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn parse_from_fixed_today() {
+        // 2024-12-25 는 수요일
+        let today = date("2024-12-25");
+        let cases: &[(&[&str], &str)] = &[
+            (&["오늘"], "2024-12-25"),
+            (&["내일"], "2024-12-26"),
+            (&["낼"], "2024-12-26"),
+            (&["모레"], "2024-12-27"),
+            (&["내일", "모레"], "2024-12-27"),
+            (&["글피"], "2024-12-28"),
+            (&["어제"], "2024-12-24"),
+            (&["그저께"], "2024-12-23"),
+            (&["3일뒤"], "2024-12-28"),
+            (&["3일", "후"], "2024-12-28"),
+            (&["10일뒤"], "2025-01-04"),
+            (&["2일전"], "2024-12-23"),
+            (&["12/31"], "2024-12-31"),
+            (&["12.1"], "2024-12-01"),
+            (&["12-25"], "2024-12-25"),
+            (&["12월25일"], "2024-12-25"),
+            (&["12월", "25일"], "2024-12-25"),
+            (&["2024-02-29"], "2024-02-29"),
+            // 연도를 안 적으면 가까운 해
+            (&["1/2"], "2025-01-02"),
+            (&["6/1"], "2025-06-01"),
+            (&["11/30"], "2024-11-30"),
+            // 주를 안 적으면 오늘부터 처음 오는 요일
+            (&["수요일"], "2024-12-25"),
+            (&["월요일"], "2024-12-30"),
+            (&["이번주", "월요일"], "2024-12-23"),
+            (&["이번주", "일요일"], "2024-12-29"),
+            (&["다음주", "월요일"], "2024-12-30"),
+            (&["다음주", "금"], "2025-01-03"),
+            (&["다다음주", "월요일"], "2025-01-06"),
+            (&["지난주", "금요일"], "2024-12-20"),
+            (&["저번주", "월요일"], "2024-12-16"),
+        ];
+        for (words, expected) in cases {
+            assert_eq!(parse(words, today), Some(date(expected)), "{:?}", words);
+        }
+    }
+
+    #[test]
+    fn parse_rejects_unknown_and_impossible_dates() {
+        let today = date("2024-12-25");
+        let cases: &[&[&str]] = &[
+            &[], &[""], &["점심"], &["월"], &["3일"], &["일뒤"], &["-3일뒤"], &["다음주"],
+            &["2/30"], &["2023-02-29"], &["13/1"], &["12/25/1"], &["12월"], &["지난주", "점심"],
+        ];
+        for words in cases {
+            assert_eq!(parse(words, today), None, "{:?}", words);
+        }
+    }
+
+    #[test]
+    fn label_has_weekday() {
+        assert_eq!(label(date("2024-12-25")), "12/25(수)");
+        assert_eq!(label(date("2025-01-05")), "01/05(일)");
+    }
+}
@@ -0,0 +1,217 @@
+// 봇 상태를 담는 data/jjambot.sqlite
+//
+// 스키마는 migrations/ 의 sqlx 마이그레이션으로 관리하고 시작할 때 적용함
+// 식단도 menus, dishes 테이블에 부대, 날짜별로 둠 (예전에는 받을 때마다 jjam-<부대코드>-<날짜>.sqlite 스냅샷을 새로 만들었음)
+// 예전 users.sqlite, orders.sqlite, archive.sqlite 와 jjam-*.sqlite 스냅샷은 처음 한 번만 가져옴 (원본 파일은 지우지 않음)
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use sqlx::migrate::Migrator;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode};
+use sqlx::{Connection, Executor, Pool, Row, Sqlite, SqliteConnection, SqlitePool};
+
+use crate::storage::replace_menus;
+use crate::{archive_jjam, default_unit, group_by_date, time_now, unit_codes, JjamRow, ShowError};
+
+pub const MAIN_DB: &str = "sqlite://data/jjambot.sqlite?mode=rwc";
+
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+// 예전 파일, 가져올 테이블
+const LEGACY_FILES: [(&str, &[&str]); 3] = [
+    ("data/users.sqlite", &["users", "timer", "allergies", "prefs", "quality"]),
+    ("data/orders.sqlite", &["orders"]),
+    ("data/archive.sqlite", &["archive"]),
+];
+const LEGACY_IMPORTED: &str = "legacy_import";
+// 예전 코드는 아침메뉴를 breakfastorder 로, 아침식집을 breakfast 로 뒤바꿔 저장했음 (migrations/0002_timer_kinds.sql 과 같음)
+const LEGACY_TIMER_WHAT: &str = "CASE what WHEN 'breakfast' THEN 'breakfastorder' WHEN 'breakfastorder' THEN 'breakfast' ELSE what END";
+const SNAPSHOTS_IMPORTED: &str = "snapshot_import";
+
+pub async fn connect() -> Result<Pool<Sqlite>, ShowError> {
+    let options = SqliteConnectOptions::from_str(MAIN_DB)?.journal_mode(SqliteJournalMode::Wal);
+    Ok(SqlitePool::connect_with(options).await?)
+}
+
+// 테스트용: 마이그레이션만 적용한 메모리 DB (연결이 끊기면 사라지므로 하나만 계속 씀)
+#[cfg(test)]
+pub async fn memory() -> Result<Pool<Sqlite>, ShowError> {
+    let db = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect("sqlite::memory:").await?;
+    MIGRATOR.run(&db).await.map_err(|e| format!("migration failed: {}", e))?;
+    Ok(db)
+}
+
+// 마이그레이션을 적용하고 아직 안 했으면 예전 파일을 가져옴
+pub async fn init() -> Result<Pool<Sqlite>, ShowError> {
+    let db = connect().await?;
+    MIGRATOR.run(&db).await.map_err(|e| format!("migration failed: {}", e))?;
+
+    if !imported(&db, LEGACY_IMPORTED).await? {
+        import_legacy(&db).await?;
+    }
+    if !imported(&db, SNAPSHOTS_IMPORTED).await? {
+        import_snapshots(&db).await?;
+    }
+    Ok(db)
+}
+
+async fn imported(db: &Pool<Sqlite>, key: &str) -> Result<bool, ShowError> {
+    Ok(sqlx::query("SELECT 1 FROM meta WHERE key=?")
+        .bind(key)
+        .fetch_optional(db).await?
+        .is_some())
+}
+
+// data/jjam-<부대코드>-YYYY-MM-DD.sqlite 목록 (오래된 것부터)
+// 부대코드가 없는 예전 형식(jjam-YYYY-MM-DD.sqlite)은 기본 부대의 것으로 취급
+fn snapshot_files(unit: &str) -> Vec<String> {
+    let is_default = unit == default_unit();
+    let mut files: Vec<(NaiveDate, String)> = Vec::new();
+
+    if let Ok(entries) = fs::read_dir("data/") {
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let fname = entry.file_name();
+            let Some(name_str) = fname.to_str() else {
+                continue;
+            };
+            let Some(stem) = name_str.strip_prefix("jjam-").and_then(|n| n.strip_suffix(".sqlite")) else {
+                continue;
+            };
+            let date_str = match stem.len().checked_sub(10).and_then(|idx| stem.split_at_checked(idx)) {
+                Some(("", date_str)) if is_default => date_str,
+                Some((file_unit, date_str)) if file_unit.strip_suffix('-') == Some(unit) => date_str,
+                _ => continue,
+            };
+
+            if let Ok(date) = NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
+                files.push((date, name_str.to_string()));
+            }
+        }
+    }
+    // 같은 날짜에 두 형식이 다 있으면 부대코드가 붙은 쪽을 나중에 (덮어쓰도록)
+    files.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.len().cmp(&b.1.len())));
+    files.into_iter().map(|(_, name)| name).collect()
+}
+
+// 스냅샷 하나의 식단 행, 읽을 수 없는 파일이면 알리고 건너뜀
+async fn read_snapshot(name: &str) -> Result<Option<Vec<JjamRow>>, ShowError> {
+    let snapshot = SqlitePool::connect(&format!("sqlite://data/{}?mode=ro", name)).await?;
+    let rows = sqlx::query_as::<_, JjamRow>("SELECT * FROM jjam ORDER BY id").fetch_all(&snapshot).await;
+    snapshot.close().await;
+    match rows {
+        Ok(rows) => Ok(Some(rows)),
+        Err(e) => {
+            eprintln!("snapshot_import({}): {}", name, e);
+            Ok(None)
+        },
+    }
+}
+
+// 스냅샷의 식단을 오래된 것부터 menus, dishes 에 넣음 (날짜별로 덮어쓰므로 최신 스냅샷 내용이 남음)
+async fn import_snapshots(db: &Pool<Sqlite>) -> Result<(), ShowError> {
+    let mut tx = db.begin().await?;
+    for unit in unit_codes() {
+        let files = snapshot_files(&unit);
+        for name in &files {
+            let Some(rows) = read_snapshot(name).await? else {
+                continue;
+            };
+            for (date, rows) in group_by_date(rows) {
+                replace_menus(&mut tx, &unit, &date, &rows).await?;
+            }
+        }
+        if !files.is_empty() {
+            println!("snapshot_import: {} snapshots for {}", files.len(), unit);
+        }
+    }
+    sqlx::query("INSERT INTO meta (key, value) VALUES (?, ?)")
+        .bind(SNAPSHOTS_IMPORTED)
+        .bind(time_now().format("%Y-%m-%d %H:%M:%S").to_string())
+        .execute(&mut *tx).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn import_legacy(db: &Pool<Sqlite>) -> Result<(), ShowError> {
+    // 스냅샷에 남아 있는 식단을 오래된 것부터 보관함에 넣음 (날짜별로 덮어쓰므로 다시 해도 같음)
+    for unit in unit_codes() {
+        for name in snapshot_files(&unit) {
+            if let Some(rows) = read_snapshot(&name).await? {
+                archive_jjam(db, &unit, &group_by_date(rows)).await?;
+            }
+        }
+    }
+
+    // ATTACH 는 연결마다 따로라서 한 연결에서 모두 처리
+    let mut conn = db.acquire().await?;
+    let mut attached = Vec::new();
+    for (idx, (file, tables)) in LEGACY_FILES.iter().enumerate() {
+        if Path::new(file).exists() {
+            let schema = format!("legacy{}", idx);
+            sqlx::query(&format!("ATTACH DATABASE ? AS {}", schema)).bind(*file).execute(&mut *conn).await?;
+            attached.push((schema, *file, *tables));
+        }
+    }
+
+    let result = copy_legacy(&mut conn, &attached).await;
+    for (schema, _, _) in &attached {
+        let _ = conn.execute(format!("DETACH DATABASE {}", schema).as_str()).await;
+    }
+    result
+}
+
+async fn copy_legacy(conn: &mut SqliteConnection, attached: &[(String, &str, &[&str])]) -> Result<(), ShowError> {
+    let mut tx = conn.begin().await?;
+    for (schema, file, tables) in attached {
+        for table in *tables {
+            // 예전 버전 파일에는 없는 열(users.unit 등)이 있을 수 있어서 양쪽에 다 있는 열만 옮김, id 는 새로 매김
+            let legacy: Vec<String> = sqlx::query("SELECT name FROM pragma_table_info(?, ?)")
+                .bind(*table).bind(schema)
+                .fetch_all(&mut *tx).await?
+                .iter()
+                .map(|r| r.get::<String, _>(0))
+                .collect();
+            if legacy.is_empty() {
+                continue;
+            }
+            let columns: Vec<String> = sqlx::query("SELECT name FROM pragma_table_info(?, 'main')")
+                .bind(*table)
+                .fetch_all(&mut *tx).await?
+                .iter()
+                .map(|r| r.get::<String, _>(0))
+                .filter(|c| c != "id" && legacy.contains(c))
+                .collect();
+            let select = columns.iter()
+                .map(|c| if *table == "timer" && c == "what" { LEGACY_TIMER_WHAT } else { c.as_str() })
+                .collect::<Vec<_>>()
+                .join(", ");
+            let columns = columns.join(", ");
+            // 사용자가 없는 예약, 설정은 외래키에 걸리므로 뺌
+            let filter = if *table != "users" && legacy.iter().any(|c| c == "userid") {
+                "WHERE userid IN (SELECT userid FROM main.users)"
+            } else {
+                ""
+            };
+            if *table == "archive" {
+                // 스냅샷에서 넣은 날짜와 겹치면 보관함 쪽으로 바꿈
+                tx.execute(format!("DELETE FROM main.archive WHERE unit || dates IN (SELECT unit || dates FROM {}.archive)", schema).as_str()).await?;
+            }
+            let copied = tx.execute(format!(
+                "INSERT OR IGNORE INTO main.{table} ({columns}) SELECT {select} FROM {schema}.{table} {filter} ORDER BY id"
+            ).as_str()).await?;
+            println!("legacy_import: {} rows from {} {}", copied.rows_affected(), file, table);
+        }
+    }
+    sqlx::query("INSERT INTO meta (key, value) VALUES (?, ?)")
+        .bind(LEGACY_IMPORTED)
+        .bind(time_now().format("%Y-%m-%d %H:%M:%S").to_string())
+        .execute(&mut *tx).await?;
+    tx.commit().await?;
+    Ok(())
+}
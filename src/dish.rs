@@ -0,0 +1,299 @@
+// 식단 문자열 한 칸을 요리 단위로 나눔
+//
+// 예: ("닭볶음탕(5)(6)(13)", Some(410.0)) -> Dish { name: "닭볶음탕", allergens: [5, 6, 13], kcal: Some(410.0) }
+// 알레르기 번호는 (5)(6) 처럼 하나씩 오거나 (5.6.13) 처럼 묶여서 오기도 함
+// 숫자가 아닌 괄호 내용은 요리 이름으로 둠 (예: "돈육장조림(국내산)")
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dish {
+    pub name: String,
+    pub allergens: Vec<u8>,
+    pub kcal: Option<f64>,
+}
+
+impl Dish {
+    pub fn allergens_str(&self) -> String {
+        self.allergens.iter().map(u8::to_string).collect::<Vec<_>>().join(",")
+    }
+}
+
+impl std::fmt::Display for Dish {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.allergens.is_empty() {
+            write!(f, " ({})", self.allergens_str())?;
+        }
+        match self.kcal {
+            Some(kcal) => write!(f, " [{}kcal]", kcal),
+            None => write!(f, " [?kcal]"),
+        }
+    }
+}
+
+pub fn parse_dish(menu: &str, kcal: Option<f64>) -> Option<Dish> {
+    let mut name = String::new();
+    let mut allergens: Vec<u8> = Vec::new();
+    let mut rest = menu.trim();
+
+    while let Some(open) = rest.find('(') {
+        let Some(close) = rest[open..].find(')').map(|idx| open + idx) else {
+            break;
+        };
+        let inner = &rest[open + 1..close];
+        let numbers: Vec<Option<u8>> = inner
+            .split(['.', ',', ' '])
+            .filter(|n| !n.is_empty())
+            .map(|n| n.parse::<u8>().ok())
+            .collect();
+
+        if !numbers.is_empty() && numbers.iter().all(Option::is_some) {
+            name.push_str(&rest[..open]);
+            allergens.extend(numbers.into_iter().flatten());
+        } else {
+            name.push_str(&rest[..=close]);
+        }
+        rest = &rest[close + 1..];
+    }
+    name.push_str(rest);
+
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    if name.is_empty() {
+        return None;
+    }
+    allergens.sort_unstable();
+    allergens.dedup();
+
+    Some(Dish { name, allergens, kcal })
+}
+
+// 칼로리 칸을 숫자로 읽음. "1,234.5 kcal", "320Kcal", "320" 모두 받고, 읽을 수 없거나 음수면 None (모름)
+pub fn parse_kcal(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let value = value
+        .strip_suffix("칼로리")
+        .or_else(|| value.len().checked_sub(4)
+            .filter(|idx| value.is_char_boundary(*idx) && value[*idx..].eq_ignore_ascii_case("kcal"))
+            .map(|idx| &value[..idx]))
+        .unwrap_or(value);
+    value.trim()
+        .replace(',', "")
+        .parse::<f64>()
+        .ok()
+        .filter(|kcal| kcal.is_finite() && *kcal >= 0.0)
+}
+
+// 요리 칼로리 합. 하나도 모르면 None, 일부만 알면 (아는 것의 합, 모르는 요리 수)
+pub fn kcal_total(dishes: &[Dish]) -> Option<(f64, usize)> {
+    let known: Vec<f64> = dishes.iter().filter_map(|d| d.kcal).collect();
+    if known.is_empty() {
+        return None;
+    }
+    Some((known.iter().sum(), dishes.len() - known.len()))
+}
+
+// 식품 등 표시기준의 알레르기 유발 식품 번호, 이름, 사용자가 입력할 만한 다른 이름
+pub const ALLERGENS: [(u8, &str, &[&str]); 19] = [
+    (1, "난류", &["계란", "달걀", "알류"]),
+    (2, "우유", &["유제품"]),
+    (3, "메밀", &[]),
+    (4, "땅콩", &[]),
+    (5, "대두", &["콩"]),
+    (6, "밀", &["밀가루"]),
+    (7, "고등어", &[]),
+    (8, "게", &["꽃게"]),
+    (9, "새우", &[]),
+    (10, "돼지고기", &["돼지", "돈육"]),
+    (11, "복숭아", &[]),
+    (12, "토마토", &[]),
+    (13, "아황산류", &["아황산"]),
+    (14, "호두", &[]),
+    (15, "닭고기", &["닭"]),
+    (16, "쇠고기", &["소고기", "소"]),
+    (17, "오징어", &[]),
+    (18, "조개류", &["조개", "굴", "전복", "홍합", "패류"]),
+    (19, "잣", &[]),
+];
+
+pub fn allergen_name(number: u8) -> &'static str {
+    ALLERGENS.iter()
+        .find(|(n, _, _)| *n == number)
+        .map(|(_, name, _)| *name)
+        .unwrap_or("?")
+}
+
+// 이름, 다른 이름, 번호 모두 받음. 갑각류는 게와 새우 둘 다
+pub fn allergen_numbers(word: &str) -> Vec<u8> {
+    if word == "갑각류" {
+        return vec![8, 9];
+    }
+    if let Ok(number) = word.parse::<u8>() {
+        return ALLERGENS.iter().filter(|(n, _, _)| *n == number).map(|(n, _, _)| *n).collect();
+    }
+    ALLERGENS.iter()
+        .filter(|(_, name, aliases)| *name == word || aliases.contains(&word))
+        .map(|(n, _, _)| *n)
+        .collect()
+}
+
+// 식성 태그: 요리 이름에 들어가는 낱말과 관련 알레르기 번호로 판단
+const PORK: &[&str] = &["돼지", "돈육", "돈까스", "돈가스", "제육", "삼겹", "목살", "족발", "보쌈", "햄", "소시지", "소세지", "베이컨", "순대", "탕수육"];
+const BEEF: &[&str] = &["소고기", "쇠고기", "우육", "불고기", "갈비탕", "육개장", "장조림", "차돌", "우삼겹"];
+const CHICKEN: &[&str] = &["닭", "치킨", "계육", "찜닭"];
+const SEAFOOD: &[&str] = &["생선", "고등어", "삼치", "꽁치", "갈치", "명태", "북어", "북엇", "동태", "코다리", "오징어", "새우", "게살", "꽃게", "조개", "바지락", "홍합", "굴", "전복", "어묵", "오뎅", "멸치", "참치", "연어", "낙지", "주꾸미", "쭈꾸미", "해물"];
+
+pub struct DietTag {
+    pub name: &'static str,
+    keywords: &'static [&'static [&'static str]],
+    allergens: &'static [u8],
+}
+
+pub const DIET_TAGS: [DietTag; 5] = [
+    DietTag { name: "돼지고기", keywords: &[PORK], allergens: &[10] },
+    DietTag { name: "소고기", keywords: &[BEEF], allergens: &[16] },
+    DietTag { name: "닭고기", keywords: &[CHICKEN], allergens: &[15] },
+    DietTag { name: "해산물", keywords: &[SEAFOOD], allergens: &[7, 8, 9, 17, 18] },
+    DietTag { name: "채식", keywords: &[PORK, BEEF, CHICKEN, SEAFOOD, &["고기", "육류"]], allergens: &[7, 8, 9, 10, 15, 16, 17, 18] },
+];
+
+pub fn diet_tag(word: &str) -> Option<&'static str> {
+    let word = match word {
+        "돼지" => "돼지고기",
+        "쇠고기" | "소" => "소고기",
+        "닭" => "닭고기",
+        "생선" | "해물" | "수산물" => "해산물",
+        "채식주의" | "베지테리언" => "채식",
+        _ => word,
+    };
+    DIET_TAGS.iter().map(|tag| tag.name).find(|name| *name == word)
+}
+
+// 요리가 tags 중 하나라도 걸리는지
+pub fn matches_diet(dish: &Dish, tags: &[String]) -> bool {
+    DIET_TAGS.iter()
+        .filter(|tag| tags.iter().any(|t| t == tag.name))
+        .any(|tag| {
+            tag.keywords.iter().flat_map(|k| k.iter()).any(|k| dish.name.contains(k))
+                || dish.allergens.iter().any(|a| tag.allergens.contains(a))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dish(name: &str, allergens: &[u8]) -> Dish {
+        Dish { name: name.to_string(), allergens: allergens.to_vec(), kcal: None }
+    }
+
+    #[test]
+    fn parse_dish_splits_allergens_from_name() {
+        let cases: &[(&str, &str, &[u8])] = &[
+            ("돈육김치찌개(5.9.13)", "돈육김치찌개", &[5, 9, 13]),
+            ("닭볶음탕(5)(6)(13)", "닭볶음탕", &[5, 6, 13]),
+            ("  우유 (2, 2) ", "우유", &[2]),
+            ("돈육장조림(국내산)", "돈육장조림(국내산)", &[]),
+            ("돈육장조림(국내산)(5.10)", "돈육장조림(국내산)", &[5, 10]),
+            ("(5)잡곡밥", "잡곡밥", &[5]),
+            ("쌀밥(1.a)", "쌀밥(1.a)", &[]),
+            ("카레라이스(5", "카레라이스(5", &[]),
+            ("김치", "김치", &[]),
+        ];
+        for (menu, name, allergens) in cases {
+            assert_eq!(parse_dish(menu, None), Some(dish(name, allergens)), "{}", menu);
+        }
+        assert_eq!(parse_dish("짜장밥(5.6)", Some(410.0)).unwrap().kcal, Some(410.0));
+        for menu in ["", "   ", "(1.2)"] {
+            assert_eq!(parse_dish(menu, None), None, "{:?}", menu);
+        }
+    }
+
+    #[test]
+    fn dish_display() {
+        let mut d = dish("짜장밥", &[5, 6]);
+        assert_eq!(d.to_string(), "짜장밥 (5,6) [?kcal]");
+        d.kcal = Some(300.5);
+        assert_eq!(d.to_string(), "짜장밥 (5,6) [300.5kcal]");
+        assert_eq!(dish("김치", &[]).to_string(), "김치 [?kcal]");
+    }
+
+    #[test]
+    fn parse_kcal_reads_numbers_and_units() {
+        let cases: &[(&str, Option<f64>)] = &[
+            ("850.3kcal", Some(850.3)),
+            ("320Kcal", Some(320.0)),
+            ("1,234.5 kcal", Some(1234.5)),
+            (" 320 ", Some(320.0)),
+            ("700칼로리", Some(700.0)),
+            ("0", Some(0.0)),
+            ("", None),
+            ("kcal", None),
+            ("-5kcal", None),
+            ("NaN", None),
+            ("inf", None),
+            ("약 300kcal", None),
+            ("열량", None),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(parse_kcal(value), *expected, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn kcal_total_counts_unknown_dishes() {
+        let with = |kcal: Option<f64>| Dish { kcal, ..dish("밥", &[]) };
+        assert_eq!(kcal_total(&[]), None);
+        assert_eq!(kcal_total(&[with(None), with(None)]), None);
+        assert_eq!(kcal_total(&[with(Some(300.0)), with(Some(10.5))]), Some((310.5, 0)));
+        assert_eq!(kcal_total(&[with(Some(300.0)), with(None)]), Some((300.0, 1)));
+    }
+
+    #[test]
+    fn allergen_numbers_and_names() {
+        assert_eq!(allergen_numbers("우유"), [2]);
+        assert_eq!(allergen_numbers("돈육"), [10]);
+        assert_eq!(allergen_numbers("13"), [13]);
+        assert_eq!(allergen_numbers("갑각류"), [8, 9]);
+        assert!(allergen_numbers("20").is_empty());
+        assert!(allergen_numbers("외계인").is_empty());
+        assert_eq!(allergen_name(16), "쇠고기");
+        assert_eq!(allergen_name(99), "?");
+    }
+
+    #[test]
+    fn diet_tags_match_names_and_allergens() {
+        assert_eq!(diet_tag("돼지"), Some("돼지고기"));
+        assert_eq!(diet_tag("쇠고기"), Some("소고기"));
+        assert_eq!(diet_tag("닭"), Some("닭고기"));
+        assert_eq!(diet_tag("생선"), Some("해산물"));
+        assert_eq!(diet_tag("베지테리언"), Some("채식"));
+        assert_eq!(diet_tag("채식"), Some("채식"));
+        assert_eq!(diet_tag("매운맛"), None);
+
+        let tags = |tag: &str| vec![tag.to_string()];
+        let cases: &[(&str, Dish, bool)] = &[
+            ("돼지고기", dish("제육볶음", &[]), true),
+            // 이름에 없어도 알레르기 번호로 걸림
+            ("돼지고기", dish("김치찌개", &[5, 10]), true),
+            ("돼지고기", dish("닭갈비", &[15]), false),
+            ("소고기", dish("불고기", &[]), true),
+            ("소고기", dish("미역국", &[16]), true),
+            ("소고기", dish("된장국", &[5]), false),
+            ("닭고기", dish("치킨까스", &[]), true),
+            ("닭고기", dish("돈까스", &[10]), false),
+            ("해산물", dish("고등어구이", &[]), true),
+            ("해산물", dish("미역국", &[18]), true),
+            ("해산물", dish("계란말이", &[1]), false),
+            ("채식", dish("소고기무국", &[]), true),
+            ("채식", dish("어묵볶음", &[]), true),
+            ("채식", dish("고기만두", &[]), true),
+            ("채식", dish("두부조림", &[5]), false),
+            ("채식", dish("쌀밥", &[]), false),
+        ];
+        for (tag, dish, expected) in cases {
+            assert_eq!(matches_diet(dish, &tags(tag)), *expected, "{} {}", tag, dish.name);
+        }
+        // 태그가 없으면 아무것도 안 걸림
+        assert!(!matches_diet(&dish("제육볶음", &[10]), &[]));
+        assert!(matches_diet(&dish("제육볶음", &[]), &[String::from("해산물"), String::from("돼지고기")]));
+    }
+}
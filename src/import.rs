@@ -0,0 +1,197 @@
+// 외부망이 안 될 때 파일로 가져온 식단을 수집한 것처럼 넣음 (파일에 있는 날짜만 바꿈)
+//
+// jjambot import <부대코드> <파일>
+// .json: openapi.mnd.go.kr 응답을 그대로 저장한 파일
+// .csv : data.mnd.go.kr 에서 받은 CSV (UTF-8), 헤더는 API 필드명이나 한글 이름 둘 다 받음
+use std::path::Path;
+
+use crate::{jjam_service, mnd, state::AppState, store_jjam, RawJjamRow, ShowError};
+
+pub async fn import_file(state: &AppState, unit: &str, path: &str) -> Result<(), ShowError> {
+    let bytes = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+    let text = String::from_utf8(bytes)
+        .map_err(|_| format!("{}: UTF-8 파일이 아닙니다. UTF-8로 변환한 뒤 다시 시도하세요", path))?;
+    let rows = parse_rows(unit, path, &text)?;
+    if rows.is_empty() {
+        return Err(format!("{}: 식단 행이 없습니다", path).into());
+    }
+
+    let count = rows.len();
+    // 파일은 일부 기간만 담고 있을 수 있으므로 없는 날짜를 지우지 않고 합치기만 함
+    let changes = store_jjam(state, unit, rows, false).await?;
+    println!("{}: {} rows imported from {} ({} upcoming meals changed)", unit, count, path, changes.len());
+    Ok(())
+}
+
+// 확장자로 CSV / JSON 을 고름, 엑셀이 붙이는 BOM 은 뗌
+fn parse_rows(unit: &str, path: &str, text: &str) -> Result<Vec<RawJjamRow>, ShowError> {
+    let text = text.trim_start_matches('\u{feff}');
+    let is_csv = Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    if is_csv {
+        parse_csv_rows(text)
+    } else {
+        Ok(mnd::parse_page::<RawJjamRow>(text, &jjam_service(unit))?.row)
+    }
+}
+
+fn parse_csv_rows(text: &str) -> Result<Vec<RawJjamRow>, ShowError> {
+    let mut records = parse_csv(text).into_iter();
+    let Some(header) = records.next() else {
+        return Ok(Vec::new());
+    };
+
+    let columns: Vec<Option<&str>> = header.iter().map(|name| column_field(name.trim())).collect();
+    if !columns.contains(&Some("dates")) {
+        return Err(format!("CSV 헤더에 날짜 열이 없습니다: {}", header.join(",")).into());
+    }
+
+    let mut rows = Vec::new();
+    for record in records {
+        if record.iter().all(|v| v.trim().is_empty()) {
+            continue;
+        }
+        let mut meal = RawJjamRow::default();
+        for (field, value) in columns.iter().zip(record) {
+            let value = value.trim().to_string();
+            match field {
+                Some("dates") => meal.dates = normalize_date(&value),
+                Some("brst") => meal.brst = value,
+                Some("brst_cal") => meal.brst_cal = value,
+                Some("lunc") => meal.lunc = value,
+                Some("lunc_cal") => meal.lunc_cal = value,
+                Some("dinr") => meal.dinr = value,
+                Some("dinr_cal") => meal.dinr_cal = value,
+                Some("adspcfd") => meal.adspcfd = value,
+                Some("adspcfd_cal") => meal.adspcfd_cal = value,
+                Some("sum_cal") => meal.sum_cal = value,
+                _ => {},
+            }
+        }
+        rows.push(meal);
+    }
+    Ok(rows)
+}
+
+// CSV 헤더 이름을 RawJjamRow 필드명으로
+fn column_field(name: &str) -> Option<&'static str> {
+    Some(match name.to_ascii_lowercase().as_str() {
+        "dates" | "날짜" | "일자" | "식단일자" => "dates",
+        "brst" | "조식" | "아침" => "brst",
+        "brst_cal" | "조식칼로리" | "조식열량" => "brst_cal",
+        "lunc" | "중식" | "점심" => "lunc",
+        "lunc_cal" | "중식칼로리" | "중식열량" => "lunc_cal",
+        "dinr" | "석식" | "저녁" => "dinr",
+        "dinr_cal" | "석식칼로리" | "석식열량" => "dinr_cal",
+        "adspcfd" | "부식" | "특식" => "adspcfd",
+        "adspcfd_cal" | "부식칼로리" | "부식열량" | "특식칼로리" => "adspcfd_cal",
+        "sum_cal" | "총칼로리" | "총열량" | "합계칼로리" => "sum_cal",
+        _ => return None,
+    })
+}
+
+// 20231225, 2023.12.25, 2023/12/25 -> 2023-12-25
+fn normalize_date(value: &str) -> String {
+    let digits: String = value.chars().take_while(|c| !c.is_whitespace()).filter(char::is_ascii_digit).collect();
+    if digits.len() == 8 {
+        format!("{}-{}-{}", &digits[0..4], &digits[4..6], &digits[6..8])
+    } else {
+        value.to_string()
+    }
+}
+
+// 따옴표로 감싼 칸 안의 쉼표, 줄바꿈, "" 이스케이프까지 처리하는 간단한 CSV 파서
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            },
+            ('"', true) => quoted = false,
+            ('"', false) if field.is_empty() => quoted = true,
+            (',', false) => record.push(std::mem::take(&mut field)),
+            ('\r', false) => {},
+            ('\n', false) => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            },
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_quoted_fields() {
+        let records = parse_csv("a,\"b, c\",\"say \"\"hi\"\"\"\r\n\"two\nlines\",,x\n");
+        assert_eq!(records, [
+            vec!["a", "b, c", "say \"hi\""],
+            vec!["two\nlines", "", "x"],
+        ]);
+        // 마지막 줄에 줄바꿈이 없어도 읽음
+        assert_eq!(parse_csv("a,b\n1,2"), [vec!["a", "b"], vec!["1", "2"]]);
+        assert!(parse_csv("").is_empty());
+    }
+
+    #[test]
+    fn csv_rows_with_bom_and_korean_headers() {
+        let text = "\u{feff}식단일자,조식,조식열량,중식,비고\n20231225,\"밥, 김치\",850.3kcal,짜장면(5.6),메모\n\n";
+        let rows = parse_rows("1111", "meals.CSV", text).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].dates, "2023-12-25");
+        assert_eq!(rows[0].brst, "밥, 김치");
+        assert_eq!(rows[0].brst_cal, "850.3kcal");
+        assert_eq!(rows[0].lunc, "짜장면(5.6)");
+    }
+
+    #[test]
+    fn csv_date_formats() {
+        for (value, expected) in [
+            ("20231225", "2023-12-25"),
+            ("2023-12-25", "2023-12-25"),
+            ("2023.12.25", "2023-12-25"),
+            ("2023/12/25", "2023-12-25"),
+            ("2023.12.25 (월)", "2023-12-25"),
+            // 읽을 수 없는 날짜는 그대로 둠
+            ("12/25", "12/25"),
+            ("", ""),
+        ] {
+            assert_eq!(normalize_date(value), expected, "{}", value);
+        }
+    }
+
+    #[test]
+    fn csv_without_date_column_is_rejected() {
+        let e = parse_csv_rows("day,brst\n20231225,밥\n").unwrap_err();
+        assert!(e.to_string().ends_with("CSV 헤더에 날짜 열이 없습니다: day,brst"), "{}", e);
+        assert_eq!(column_field("LUNC_CAL"), Some("lunc_cal"));
+        assert_eq!(column_field("day"), None);
+        assert!(parse_csv_rows("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn json_response_uses_unit_dataset() {
+        let text = r#"{"DS_TB_MNDT_DATEBYMLSVC_1111": {"list_total_count": 1, "row": [{
+            "dates": "2023-12-25", "brst": "밥", "brst_cal": "", "lunc": "", "lunc_cal": "", "dinr": "", "dinr_cal": "",
+            "adspcfd": "", "adspcfd_cal": "", "sum_cal": ""
+        }]}}"#;
+        let rows = parse_rows("1111", "meals.json", text).unwrap();
+        assert_eq!((rows[0].dates.as_str(), rows[0].brst.as_str()), ("2023-12-25", "밥"));
+    }
+}
@@ -0,0 +1,133 @@
+// 예약 종류
+//
+// 예약, 삭제, 예약 목록, 알람, 식단 변경 알림이 모두 이 표 하나를 봄
+// timer.what 에는 what 만 저장하므로 종류를 늘릴 때는 여기에 한 줄 추가하면 됨
+use crate::MealType;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Content {
+    // 그 끼니 식단
+    Menu,
+    // 식사순서, 평일에는 weekday_floor 층, 주말에는 1(2층) 순서를 보냄
+    Order { weekday_floor: usize },
+}
+
+#[derive(Debug)]
+pub struct Kind {
+    // timer.what 값
+    pub what: &'static str,
+    // 안내에 쓰는 이름, 첫 번째가 대표
+    pub aliases: &'static [&'static str],
+    // 시간을 안 적었을 때 알림 시각 (HH:MM)
+    pub default_time: &'static str,
+    pub meal: MealType,
+    pub content: Content,
+    // 0 이면 오늘, 1 이면 내일 식단
+    pub days: i64,
+    // 토요일에는 점심이 없어서 보내지 않음
+    pub skip_saturday: bool,
+}
+
+impl Kind {
+    pub fn name(&self) -> &'static str {
+        self.aliases[0]
+    }
+
+    // 식단 알림 첫 줄 (예: "내일 아침 메뉴")
+    pub fn menu_title(&self) -> String {
+        format!("{}{} 메뉴", if self.days == 1 { "내일 " } else { "" }, self.meal.name())
+    }
+}
+
+pub const KINDS: &[Kind] = &[
+    Kind { what: "breakfast", aliases: &["아침메뉴", "아침식사"], default_time: "07:15", meal: MealType::Breakfast, content: Content::Menu, days: 0, skip_saturday: false },
+    Kind { what: "breakfastorder", aliases: &["아침식집", "아침식집순서", "아침식사순서"], default_time: "07:15", meal: MealType::Breakfast, content: Content::Order { weekday_floor: 1 }, days: 0, skip_saturday: false },
+    Kind { what: "lunch", aliases: &["점심메뉴", "점심식사"], default_time: "11:00", meal: MealType::Lunch, content: Content::Menu, days: 0, skip_saturday: true },
+    Kind { what: "lunchorder", aliases: &["점심식집", "점심식집순서", "점심식사순서"], default_time: "11:00", meal: MealType::Lunch, content: Content::Order { weekday_floor: 0 }, days: 0, skip_saturday: true },
+    Kind { what: "dinner", aliases: &["저녁메뉴", "저녁식사"], default_time: "17:00", meal: MealType::Dinner, content: Content::Menu, days: 0, skip_saturday: false },
+    Kind { what: "dinnerorder", aliases: &["저녁식집", "저녁식집순서", "저녁식사순서"], default_time: "17:00", meal: MealType::Dinner, content: Content::Order { weekday_floor: 1 }, days: 0, skip_saturday: false },
+    Kind { what: "nextbreakfast", aliases: &["익일아침메뉴", "익일아침식사", "내일아침메뉴"], default_time: "20:00", meal: MealType::Breakfast, content: Content::Menu, days: 1, skip_saturday: false },
+    Kind { what: "nextbreakfastorder", aliases: &["익일아침식집", "익일아침식집순서", "익일아침식사순서"], default_time: "20:00", meal: MealType::Breakfast, content: Content::Order { weekday_floor: 1 }, days: 1, skip_saturday: false },
+];
+
+// 사용자가 적은 이름으로 찾음
+pub fn find(alias: &str) -> Option<&'static Kind> {
+    KINDS.iter().find(|k| k.aliases.contains(&alias))
+}
+
+// 저장된 timer.what 으로 찾음 (예전에 쓰던 brunch 같은 값은 None)
+pub fn by_what(what: &str) -> Option<&'static Kind> {
+    KINDS.iter().find(|k| k.what == what)
+}
+
+// 이 끼니 식단이 바뀌었을 때 알려줄 예약 종류
+// 식사순서만 예약한 사람은 빼고, 간식은 저녁 답장에 같이 붙으므로 저녁 메뉴를 예약한 사람에게 보냄
+pub fn for_meal(meal: MealType) -> Vec<&'static str> {
+    let meal = if meal == MealType::Snack { MealType::Dinner } else { meal };
+    KINDS.iter().filter(|k| k.meal == meal && k.content == Content::Menu).map(|k| k.what).collect()
+}
+
+// 사용법 안내용 대표 이름 목록
+pub fn names() -> String {
+    KINDS.iter().map(Kind::name).collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use sqlx::{migrate::Migrator, sqlite::SqlitePoolOptions, Row};
+
+    use super::*;
+
+    #[test]
+    fn aliases_and_what_round_trip() {
+        for kind in KINDS {
+            assert_eq!(by_what(kind.what).unwrap().what, kind.what);
+            // 이름이 겹치면 앞의 종류로만 찾아지므로 별칭마다 자기 종류가 나와야 함
+            for alias in kind.aliases {
+                assert_eq!(find(alias).unwrap().what, kind.what, "{}", alias);
+            }
+            assert_eq!(KINDS.iter().filter(|k| k.what == kind.what).count(), 1, "{}", kind.what);
+        }
+        assert_eq!(find("아침메뉴").unwrap().content, Content::Menu);
+        assert_eq!(find("아침식집").unwrap().content, Content::Order { weekday_floor: 1 });
+        assert!(find("brunch").is_none());
+        assert!(by_what("brunch").is_none());
+        assert!(by_what("sundaybreakfast").is_none());
+    }
+
+    #[test]
+    fn menu_kinds_per_meal() {
+        assert_eq!(for_meal(MealType::Breakfast), ["breakfast", "nextbreakfast"]);
+        assert_eq!(for_meal(MealType::Lunch), ["lunch"]);
+        assert_eq!(for_meal(MealType::Dinner), ["dinner"]);
+        assert_eq!(for_meal(MealType::Snack), ["dinner"]);
+    }
+
+    #[tokio::test]
+    async fn migration_swaps_legacy_breakfast_kinds() {
+        let db = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+        let before = Migrator {
+            migrations: Cow::Owned(crate::db::MIGRATOR.migrations[..1].to_vec()),
+            ignore_missing: false,
+            locking: true,
+        };
+        before.run(&db).await.unwrap();
+        sqlx::query("INSERT INTO users (userid) VALUES (1), (2)").execute(&db).await.unwrap();
+        // 1 은 아침메뉴(예전에는 breakfastorder 로 저장), 2 는 둘 다
+        sqlx::query(r#"INSERT INTO timer (userid, timewhen, what) VALUES
+            (1, '07:15', 'breakfastorder'), (1, '11:00', 'lunch'),
+            (2, '07:00', 'breakfast'), (2, '07:30', 'breakfastorder')"#)
+            .execute(&db).await.unwrap();
+
+        crate::db::MIGRATOR.run(&db).await.unwrap();
+        let rows: Vec<(i64, String, String)> = sqlx::query("SELECT userid, timewhen, what FROM timer ORDER BY userid, timewhen")
+            .fetch_all(&db).await.unwrap()
+            .iter()
+            .map(|r| (r.get(0), r.get(1), r.get(2)))
+            .collect();
+        let expected = [(1, "07:15", "breakfast"), (1, "11:00", "lunch"), (2, "07:00", "breakfastorder"), (2, "07:30", "breakfast")];
+        assert_eq!(rows, expected.map(|(u, t, w)| (u, t.to_string(), w.to_string())));
+    }
+}
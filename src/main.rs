@@ -1,18 +1,43 @@
 //use std::fs::File;
 //use std::io::Read;
-use std::fs;
+use std::collections::{BTreeMap, HashMap};
 use serde::{Deserialize, Serialize};
-use std::{cell::RefCell, sync::Mutex};
+use std::sync::{Arc, Mutex};
 use chrono::{Datelike, Duration, DurationRound as _, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
-use teloxide::{prelude::*, types::ParseMode};
-use sqlx::{migrate::MigrateDatabase, Executor, Pool, Row, Sqlite, SqlitePool};
+use teloxide::{prelude::*, utils::command::BotCommands};
+use sqlx::{sqlite::SqliteRow, Pool, Row, Sqlite};
 use tokio::time::sleep;
 
+mod backup;
+mod commands;
+mod dates;
+mod db;
+mod dish;
+mod import;
+mod kinds;
+#[cfg(test)]
+mod memory;
+mod mnd;
+mod postgres;
+mod quality;
+mod schedule;
+mod state;
+mod storage;
+use dish::{allergen_name, kcal_total, matches_diet, parse_dish, parse_kcal, Dish};
+use commands::Command;
+use kinds::Content;
+use mnd::{MndClient, MndError, PagedFetch};
+use quality::Finding;
+use schedule::Schedule;
+use state::AppState;
+use storage::Storage;
+
 #[derive(Debug)]
 enum ShowError {
     Database(sqlx::Error),
     Telegram(teloxide::RequestError),
     Reqwest(reqwest::Error),
+    Mnd(MndError),
     Other(String),
 }
 
@@ -22,6 +47,7 @@ impl std::fmt::Display for ShowError {
             ShowError::Database(e) => write!(f, "SQLx Error: {}", e),
             ShowError::Telegram(e) => write!(f, "Teloxide Error: {}", e),
             ShowError::Reqwest(e) => write!(f, "Reqwest Error: {}", e),
+            ShowError::Mnd(e) => write!(f, "MND OpenAPI Error: {}", e),
             ShowError::Other(item) => write!(f, "Other Error: {}", item),
         }
     }
@@ -39,6 +65,22 @@ impl From<reqwest::Error> for ShowError {
     }
 }
 
+impl From<MndError> for ShowError {
+    fn from(value: MndError) -> Self {
+        Self::Mnd(value)
+    }
+}
+
+impl ShowError {
+    // 수집을 다시 시도할 만한 오류인지, 인증키나 트래픽 제한 같은 API 오류만 아니라고 봄
+    fn is_transient(&self) -> bool {
+        match self {
+            ShowError::Mnd(e) => e.is_transient(),
+            _ => true,
+        }
+    }
+}
+
 impl From<teloxide::RequestError> for ShowError {
     fn from(value: teloxide::RequestError) -> Self {
         Self::Telegram(value)
@@ -51,26 +93,9 @@ impl From<String> for ShowError {
     }
 }
 
-impl ShowError {
-    fn tel_err(self) -> teloxide::RequestError {
-        match self {
-            Self::Telegram(v) => v,
-            ShowError::Database(e) => {
-                teloxide::RequestError::Io(std::io::Error::other(e.to_string()))
-            },
-            Self::Reqwest(e) => {
-                teloxide::RequestError::Io(std::io::Error::other(e.to_string()))
-            },
-            ShowError::Other(e) => {
-                teloxide::RequestError::Io(std::io::Error::other(e))
-            },
-        }
-    }
-}
-
-
-#[derive(Serialize, Deserialize, sqlx::FromRow, Clone, Debug)]
-struct JjamRow {
+// API 응답이나 가져온 파일의 행 그대로 (칼로리 칸도 문자열, 식단과 칼로리가 뒤바뀐 경우도 있음)
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+struct RawJjamRow {
     dates: String,
     brst: String,
     brst_cal: String,
@@ -83,54 +108,164 @@ struct JjamRow {
     sum_cal: String,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-struct JjamData {
-    list_total_count: i32,
-    row: RefCell<Vec<JjamRow>>,
+// 정리해서 menus 테이블에 저장하는 행. 칼로리는 kcal 숫자, 모르면 None
+#[derive(Clone, Debug, Default)]
+struct JjamRow {
+    dates: String,
+    brst: String,
+    brst_cal: Option<f64>,
+    lunc: String,
+    lunc_cal: Option<f64>,
+    dinr: String,
+    dinr_cal: Option<f64>,
+    adspcfd: String,
+    adspcfd_cal: Option<f64>,
+    sum_cal: Option<f64>,
+}
+
+impl<'r> sqlx::FromRow<'r, SqliteRow> for JjamRow {
+    fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            dates: row.try_get("dates")?,
+            brst: row.try_get("brst")?,
+            brst_cal: kcal_column(row, "brst_cal")?,
+            lunc: row.try_get("lunc")?,
+            lunc_cal: kcal_column(row, "lunc_cal")?,
+            dinr: row.try_get("dinr")?,
+            dinr_cal: kcal_column(row, "dinr_cal")?,
+            adspcfd: row.try_get("adspcfd")?,
+            adspcfd_cal: kcal_column(row, "adspcfd_cal")?,
+            sum_cal: kcal_column(row, "sum_cal")?,
+        })
+    }
+}
+
+// 예전 스냅샷은 칼로리를 TEXT로 저장했으므로 REAL이 아니면 문자열로 읽어서 변환
+fn kcal_column(row: &SqliteRow, column: &str) -> Result<Option<f64>, sqlx::Error> {
+    if let Ok(kcal) = row.try_get::<Option<f64>, _>(column) {
+        return Ok(kcal);
+    }
+    Ok(row.try_get::<Option<String>, _>(column)?.as_deref().and_then(parse_kcal))
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum MealType {
     Breakfast,
     Lunch,
     Dinner,
+    // 부식 (adspcfd), 따로 끼니는 아니고 저녁 답장에 같이 붙여서 보냄
+    Snack,
 }
 
 impl MealType {
-    fn get_data<'a>(&self, jjam: &'a JjamRow) -> (&'a str, &'a str) {
+    fn get_data<'a>(&self, jjam: &'a JjamRow) -> (&'a str, Option<f64>) {
         match self {
-            MealType::Breakfast => (&jjam.brst, &jjam.brst_cal),
-            MealType::Lunch => (&jjam.lunc, &jjam.lunc_cal),
-            MealType::Dinner => (&jjam.dinr, &jjam.dinr_cal),
+            MealType::Breakfast => (&jjam.brst, jjam.brst_cal),
+            MealType::Lunch => (&jjam.lunc, jjam.lunc_cal),
+            MealType::Dinner => (&jjam.dinr, jjam.dinr_cal),
+            MealType::Snack => (&jjam.adspcfd, jjam.adspcfd_cal),
         }
     }
+
+    fn name(&self) -> &'static str {
+        match self {
+            MealType::Breakfast => "아침",
+            MealType::Lunch => "점심",
+            MealType::Dinner => "저녁",
+            MealType::Snack => "간식",
+        }
+    }
+
+    // dishes, archive 테이블의 meal 값
+    fn column(&self) -> &'static str {
+        match self {
+            MealType::Breakfast => "brst",
+            MealType::Lunch => "lunc",
+            MealType::Dinner => "dinr",
+            MealType::Snack => "adspcfd",
+        }
+    }
+
+    fn from_column(column: &str) -> MealType {
+        match column {
+            "brst" => MealType::Breakfast,
+            "lunc" => MealType::Lunch,
+            "dinr" => MealType::Dinner,
+            _ => MealType::Snack,
+        }
+    }
+
+    // 하루치 식단에서 이 끼니의 요리 목록을 꺼냄
+    fn dishes(&self, jjams: &[JjamRow]) -> Vec<Dish> {
+        jjams.iter()
+            .filter_map(|r| {
+                let (menu, calorie) = self.get_data(r);
+                parse_dish(menu, calorie)
+            })
+            .collect()
+    }
 }
 
+// 이미 올라온 식단이 바뀐 내역
+struct MenuChange {
+    date: NaiveDate,
+    meal: MealType,
+    before: Vec<String>,
+    after: Vec<String>,
+}
 
-#[allow(non_snake_case)]
-#[derive(Serialize, Deserialize)]
-struct JjamDataShell {
-    DS_TB_MNDT_DATEBYMLSVC_7021: JjamData,
-} 
+impl MenuChange {
+    fn message(&self) -> String {
+        let mut msg = format!("<b>식단 변경 알림</b>\n{} {}", self.date.format("%m/%d"), self.meal.name());
+        let mut after = self.after.clone();
+        for dish in &self.before {
+            match after.iter().position(|d| d == dish) {
+                Some(idx) => { after.remove(idx); },
+                None => msg = format!("{}\n- {}", msg, dish),
+            }
+        }
+        for dish in after {
+            msg = format!("{}\n+ {}", msg, dish);
+        }
+        msg
+    }
+}
 
-#[allow(dead_code)]
-#[derive(sqlx::FromRow, Clone, Debug)]
-struct UserData {
-    userid: u64,
-    username: String,
-    realname: String,
+// 부대별 식단 데이터셋의 서비스명
+fn jjam_service(unit: &str) -> String {
+    format!("DS_TB_MNDT_DATEBYMLSVC_{}", unit)
 }
 
-#[allow(dead_code)]
-#[derive(sqlx::FromRow, Clone, Debug)]
-struct TimerData {
-    userid: u64,
-    when: NaiveTime,
-    what: String
+#[derive(Clone, Debug, Default)]
+struct PollHealth {
+    last_attempt: Option<NaiveDateTime>,
+    last_success: Option<NaiveDateTime>,
+    failures: u32,
+    last_error: Option<String>,
 }
 
+
 const TG_TOKEN: &str = "TELOXIDE_TOKEN";
 const JJAM_TOKEN: &str = "JJAM_TOKEN";
+// 쉼표로 구분하여 여러 부대코드를 지정할 수 있음 (첫 번째가 기본 부대)
 const UNIT_CODE: &str = "UNIT_CODE";
+// 페이지 요청 실패시 재시도 횟수, 받아둔 페이지는 유지하고 실패한 페이지부터 다시 받음
+const JJAM_FETCH_RETRY: u32 = 3;
+// 부대별 수집 자체를 다시 시도하는 횟수, 넘어가면 다음 정기 수집까지 기존 데이터 사용
+const JJAM_POLL_RETRY: u32 = 5;
+// 한 번에 요청할 행 수, 생략하면 API 최대값 (통합 테스트에서 여러 페이지로 나눠 받을 때 씀)
+const JJAM_PAGE_SIZE: &str = "JJAM_PAGE_SIZE";
+// 오늘부터 며칠 뒤 식단까지 변경 알림을 보낼지
+const JJAM_NOTIFY_DAYS: i64 = 7;
+// 며칠 전 식단까지 menus 에 남길지, 생략하면 전부 남김
+const JJAM_KEEP_DAYS: &str = "JJAM_KEEP_DAYS";
+// 식단 수집 시각 (schedule.rs 참고), 봇 시간대 기준
+const JJAM_POLL_SCHEDULE: &str = "JJAM_POLL_SCHEDULE";
+const DEFAULT_POLL_SCHEDULE: &str = "0 6 * * *";
+const JJAM_TZ_OFFSET: &str = "JJAM_TZ_OFFSET";
+const DEFAULT_TZ_OFFSET_HOURS: i64 = 9;
+// 관리자 알림에 적는 검사 결과 최대 줄 수
+const QUALITY_REPORT_LINES: usize = 15;
 const USER_AGENT_FIREFOX: &str = "Mozilla/5.0 (X11; Linux x86_64;rv:60.0) Gecko/20100101 Firefox/81.0";
 
 // 식사집합 순서 배열
@@ -139,507 +274,291 @@ const ORDERS: [&str; 3] = [ "본-1-2", "2-본-1", "1-2-본" ];
 
 
 const HM_00_00: Option<NaiveTime> = NaiveTime::from_hms_nano_opt(0, 0, 0, 0);
-const HM_07_15: Option<NaiveTime> = NaiveTime::from_hms_nano_opt(7, 15, 0, 0);
 const HM_08_00: Option<NaiveTime> = NaiveTime::from_hms_nano_opt(8, 0, 0, 0);
 const HM_11_00: Option<NaiveTime> = NaiveTime::from_hms_nano_opt(11, 0, 0, 0);
 const HM_12_00: Option<NaiveTime> = NaiveTime::from_hms_nano_opt(12, 0, 0, 0);
-const HM_17_00: Option<NaiveTime> = NaiveTime::from_hms_nano_opt(17, 0, 0, 0);
 const HM_18_00: Option<NaiveTime> = NaiveTime::from_hms_nano_opt(18, 0, 0, 0);
-const HM_20_00: Option<NaiveTime> = NaiveTime::from_hms_nano_opt(20, 0, 0, 0);
 
 use lazy_static::lazy_static;
 
 lazy_static! {
     // 식사집합 순서를 두 곳에서 별도로 관리
     static ref ORDERIDX: Mutex<[usize; 2]> = Mutex::new([0, 0]);
+    // 부대별 식단 수집 상태
+    static ref POLL_HEALTH: Mutex<HashMap<String, PollHealth>> = Mutex::new(HashMap::new());
+    // 봇이 쓰는 시간대, 컨테이너 TZ와 상관없이 이 값으로 계산
+    static ref TZ_OFFSET: Duration = tz_offset();
+    // 식단 수집 시각
+    static ref POLL_SCHEDULE: Schedule = poll_schedule();
 }
 
+// UTC 시각에 봇 시간대를 더한 값 (Utc 타입이지만 실제로는 현지 시각)
 fn time_now() -> chrono::DateTime<chrono::Utc> {
-    chrono::offset::Utc::now() + chrono::Duration::hours(9)
+    chrono::offset::Utc::now() + *TZ_OFFSET
 }
 
-async fn jjamdb_path(mode: &str) -> (Option<String>, Option<String>) {
-    if let Ok(entries) = fs::read_dir("data/") {
-        let file_names: Vec<_> = entries
-            .filter_map(|entry| entry.ok().map(|e| e.file_name()))
-            .collect();
+// JJAM_TZ_OFFSET: "+09:00", "9", "-03:30" 등, 생략하거나 잘못되면 한국 시간
+fn tz_offset() -> Duration {
+    let Ok(value) = std::env::var(JJAM_TZ_OFFSET) else {
+        return Duration::hours(DEFAULT_TZ_OFFSET_HOURS);
+    };
+    parse_tz_offset(&value).unwrap_or_else(|| {
+        eprintln!("invalid {}: {}, using +{:02}:00", JJAM_TZ_OFFSET, value, DEFAULT_TZ_OFFSET_HOURS);
+        Duration::hours(DEFAULT_TZ_OFFSET_HOURS)
+    })
+}
 
-        let mut latest_date: Option<NaiveDate> = None;
-        let mut second_date: Option<NaiveDate> = None;
-        for fname in file_names {
-            if let Some(name_str) = fname.to_str() {
-                if name_str.starts_with("jjam-") && name_str.ends_with(".sqlite") {
-                    let date_str = &name_str[5..15];
-
-                    if let Ok(date) = NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
-                        if let Some(current_date) = latest_date.as_ref() {
-                            if date > *current_date {
-                                second_date = latest_date;
-                                latest_date = Some(date);
-                            }
-                        } else {
-                            latest_date = Some(date);
-                        }
-                    }
-                }
-            }
-        }
-        if let Some(date1) = latest_date {
-            if let Some(date2) = second_date {
-                return (
-                    Some(format!("sqlite://data/jjam-{}.sqlite?mode={}", date1.format("%Y-%m-%d"), mode)),
-                    Some(format!("sqlite://data/jjam-{}.sqlite?mode={}", date2.format("%Y-%m-%d"), mode))
-                );
-            }
-            return (
-                Some(format!("sqlite://data/jjam-{}.sqlite?mode={}", date1.format("%Y-%m-%d"), mode)),
-                None
-        );
-        }
+fn parse_tz_offset(value: &str) -> Option<Duration> {
+    let (sign, rest) = match value.trim().strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, value.trim().trim_start_matches('+')),
+    };
+    let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+    match (hours.parse::<i64>(), minutes.parse::<i64>()) {
+        (Ok(h), Ok(m)) if h <= 14 && m < 60 => Some(Duration::minutes(sign * (h * 60 + m))),
+        _ => None,
     }
-    (None, None)
 }
 
-async fn get_jjam(date: chrono::NaiveDate) -> Result<Vec<JjamRow>, ShowError> {
-    let dburi = jjamdb_path("ro").await;
-    let mut ret: Vec<JjamRow> = Vec::new();
-    
-    if let Some(first) = dburi.0 {
-        let db = SqlitePool::connect(&first).await?;
-        let rows = sqlx::query_as::<_, JjamRow>("SELECT * FROM jjam WHERE dates=?")
-            .bind(date.format("%Y-%m-%d").to_string()).fetch_all(&db).await?;
-        
-        if rows.len() > 0 {
-            // 결과가 있으면 처리하고 반환
-            for meal in rows {
-                ret.push(meal);
-            }
-            return Ok(ret);
-        }
+fn poll_schedule() -> Schedule {
+    let value = std::env::var(JJAM_POLL_SCHEDULE).unwrap_or(DEFAULT_POLL_SCHEDULE.to_string());
+    value.parse().unwrap_or_else(|e| {
+        eprintln!("invalid {}: {}, using {}", JJAM_POLL_SCHEDULE, e, DEFAULT_POLL_SCHEDULE);
+        DEFAULT_POLL_SCHEDULE.parse().unwrap()
+    })
+}
+
+fn unit_codes() -> Vec<String> {
+    std::env::var(UNIT_CODE)
+        .unwrap_or_default()
+        .split(',')
+        .map(|code| code.trim().to_string())
+        .filter(|code| !code.is_empty())
+        .collect()
+}
+
+fn default_unit() -> String {
+    unit_codes().into_iter().next().unwrap_or_default()
+}
+
+// 관리 식단: 부대별로 저장된 식단 날짜 범위와 날짜 수, 행 수
+async fn list_menus(state: &AppState) -> Result<String, ShowError> {
+    let mut reply = String::from("<b>저장된 식단</b>");
+    for unit in unit_codes() {
+        reply = match state.storage.menu_range(&unit).await? {
+            Some(range) => format!("{}\n{}: {} ~ {} ({}일, {}행)", reply, unit, range.first, range.last, range.dates, range.rows),
+            None => format!("{}\n{}: 없음", reply, unit),
+        };
     }
-    
-    if let Some(second) = dburi.1 {
-        let db = SqlitePool::connect(&second).await?;
-        let rows = sqlx::query_as::<_, JjamRow>("SELECT * FROM jjam WHERE dates=?")
-            .bind(date.format("%Y-%m-%d").to_string()).fetch_all(&db).await?;
-        
-        for meal in rows {
-            ret.push(meal);
-        }
+    if let Some(days) = state.config.keep_days {
+        reply = format!("{}\n\n{}일보다 오래된 식단은 지움 (보관함에는 남음)", reply, days);
     }
-    
-    Ok(ret)
+    Ok(reply)
 }
 
+async fn get_jjam(state: &AppState, unit: &str, date: chrono::NaiveDate) -> Result<Vec<JjamRow>, ShowError> {
+    state.storage.menus(unit, date).await
+}
 
-async fn get_menus(jjams: &Vec<JjamRow>, word: &str, meal: MealType) -> Result<String, ShowError> {
-    let mut menus = format!("<b>{}</b> [{}kcal]\n", word, jjams[0].sum_cal);
-    for r in jjams {
-        let (menu, calorie) = meal.get_data(r);
-        if menu.is_empty() && calorie.is_empty() {
-            continue;
+// 사용자가 선택한 부대코드, 없으면 기본 부대
+async fn user_unit(id: i64, storage: &dyn Storage) -> String {
+    storage.user_unit(id).await
+        .ok()
+        .flatten()
+        .filter(|unit| unit_codes().contains(unit))
+        .unwrap_or_else(default_unit)
+}
+
+
+// 식단 답장에 반영할 사용자 설정
+#[derive(Clone, Debug, Default)]
+struct Profile {
+    // 알레르기 번호
+    allergies: Vec<u8>,
+    // 피하는 음식 태그 (dish::DIET_TAGS)
+    avoids: Vec<String>,
+}
+
+// 알레르기 요리는 ⚠️, 피하는 음식은 ✖ 로 표시하고 마지막에 주의 문구와 추천 끼니를 붙임
+async fn get_menus(jjams: &[JjamRow], word: &str, meal: MealType, profile: &Profile) -> Result<String, ShowError> {
+    // 올라온 sum_cal 대신 요리별 칼로리를 직접 더함
+    let dishes = meal.dishes(jjams);
+    let day: Vec<Dish> = [MealType::Breakfast, MealType::Lunch, MealType::Dinner, MealType::Snack].iter()
+        .flat_map(|m| m.dishes(jjams))
+        .collect();
+    let mut menus = format!("<b>{}</b> [{} / 하루 {}]\n", word, kcal_label(&dishes), kcal_label(&day));
+    let mut warned: Vec<u8> = Vec::new();
+    if dishes.is_empty() && matches!(meal, MealType::Snack) {
+        menus = format!("{}\n간식이 없는 날입니다.", menus);
+    }
+    for dish in dishes {
+        menus = format!("{}\n{}", menus, dish_line(&dish, profile, &mut warned));
+    }
+    // 부식이 있는 날은 저녁 답장 밑에 같이 보여줌
+    let snacks = MealType::Snack.dishes(jjams);
+    if matches!(meal, MealType::Dinner) && !snacks.is_empty() {
+        menus = format!("{}\n\n<b>간식</b> [{}]", menus, kcal_label(&snacks));
+        for dish in snacks {
+            menus = format!("{}\n{}", menus, dish_line(&dish, profile, &mut warned));
         }
-        menus = format!("{}\n{} [{}kcal]", menus, menu, calorie);
+    }
+    if !warned.is_empty() {
+        warned.sort_unstable();
+        warned.dedup();
+        let names: Vec<&str> = warned.iter().map(|a| allergen_name(*a)).collect();
+        menus = format!("{}\n\n⚠️ 알레르기 주의: {}", menus, names.join(", "));
+    }
+    if let Some(best) = best_meal(jjams, profile).filter(|_| !matches!(meal, MealType::Snack)) {
+        menus = format!("{}\n\n추천 끼니: {} (피할 요리가 가장 적음)", menus, best.name());
     }
     Ok(menus)
 }
 
-async fn add_book(id: i64, timewhen: &str, booktype: &str, db: &sqlx::Pool<Sqlite>) -> Result<(), ShowError> {
-    sqlx::query("INSERT OR REPLACE INTO timer (userid, timewhen, what) VALUES (?, ?, ?);")
-        .bind(id)
-        .bind(timewhen)
-        .bind(booktype)
-        .execute(db).await?;
-    Ok(())
+// 알레르기 요리는 굵게, 피하는 음식은 취소선. 걸린 알레르기 번호는 warned 에 모음
+fn dish_line(dish: &Dish, profile: &Profile, warned: &mut Vec<u8>) -> String {
+    let hits: Vec<u8> = dish.allergens.iter().copied().filter(|a| profile.allergies.contains(a)).collect();
+    if !hits.is_empty() {
+        warned.extend(hits);
+        format!("⚠️ <b>{}</b>", dish)
+    } else if matches_diet(dish, &profile.avoids) {
+        format!("✖ <s>{}</s>", dish)
+    } else {
+        dish.to_string()
+    }
 }
 
-async fn del_book(id: i64, booktype: &str, db: &sqlx::Pool<Sqlite>) -> Result<(), ShowError> {
-    sqlx::query("DELETE FROM timer WHERE userid=? AND what=?;")
-        .bind(id)
-        .bind(booktype)
-        .execute(db).await?;
-    Ok(())
+// 예: 870kcal, 모르는 요리가 섞여 있으면 870kcal+?, 전부 모르면 ?kcal
+fn kcal_label(dishes: &[Dish]) -> String {
+    match kcal_total(dishes) {
+        None => "?kcal".to_string(),
+        Some((total, 0)) => format!("{:.0}kcal", total),
+        Some((total, _)) => format!("{:.0}kcal+?", total),
+    }
 }
 
-async fn tg_reply_daemon() -> Result<(), ShowError> {
-    let bot = Bot::from_env();
-    if !Sqlite::database_exists("sqlite://data/users.sqlite?mode=ro").await.unwrap_or(false) {
-        match Sqlite::create_database("sqlite://data/users.sqlite").await {
-            Ok(_) => {
-                println!("Database created");
-                let db = SqlitePool::connect("sqlite://data/users.sqlite?mode=rw").await?;
-                db.execute("PRAGMA foreign_keys=OFF;").await?;
-                db.execute(r#"CREATE TABLE users(
-                    id INTEGER PRIMARY KEY,
-                    userid INTEGER not null UNIQUE,
-                    username INTEGER DEFAULT null,
-                    realname INTEGER DEFAULT null,
-                    admin INTEGER NOT NULL CHECK(admin IN (0, 1)) DEFAULT 0
-                );"#).await?;
-
-                db.execute(r#"CREATE TABLE timer(
-                id INTEGER PRIMARY KEY,
-                userid INTEGER not null,
-                timewhen TEXT not null,
-                what TEXT CHECK(what IN ('breakfast', 'breakfastorder', 'nextbreakfast', 'nextbreakfastorder', 'lunch', 'lunchorder', 'dinner', 'dinnerorder', 'brunch', 'brunchorder', 'sundaybreakfast', 'sundaybreakfastorder')),
-                UNIQUE (userid, what),
-                FOREIGN KEY (userid) REFERENCES users(userid)
-                );"#).await?;
-            },
-            Err(e) => {
-                eprintln!("Error creating database: {}", e);
-                return Ok(());
+// 피하는 음식, 알레르기 요리 비율이 가장 낮은 끼니. 설정이 없거나 끼니마다 차이가 없으면 None
+fn best_meal(jjams: &[JjamRow], profile: &Profile) -> Option<MealType> {
+    if profile.allergies.is_empty() && profile.avoids.is_empty() {
+        return None;
+    }
+    let scores: Vec<(MealType, f64)> = [MealType::Breakfast, MealType::Lunch, MealType::Dinner].into_iter()
+        .filter_map(|meal| {
+            let dishes = meal.dishes(jjams);
+            if dishes.is_empty() {
+                return None;
             }
-        }
+            let bad = dishes.iter()
+                .filter(|d| d.allergens.iter().any(|a| profile.allergies.contains(a)) || matches_diet(d, &profile.avoids))
+                .count();
+            Some((meal, bad as f64 / dishes.len() as f64))
+        })
+        .collect();
+    let best = scores.iter().min_by(|a, b| a.1.total_cmp(&b.1))?;
+    if scores.iter().all(|s| s.1 == best.1) {
+        return None;
     }
-    teloxide::repl(bot, |bot: Bot, msg: Message| async move {
-        let db = SqlitePool::connect("sqlite://data/users.sqlite?mode=rw").await.map_err(|e| ShowError::from(e).tel_err())?;
-        let words: Vec<&str> = msg.text().unwrap_or("").split_whitespace().collect();
-        'done:{
-            match words[0] {
-                "/start" => {
-                    bot.send_message(msg.chat.id, "환영합니다! help로 도움말을 확인하세요!").await?;
-                    let _ = sqlx::query(
-                        if words.len() == 3 && words[1] == "admin" && words[2] == "true" {
-                            "INSERT INTO users (userid, username, realname, admin) VALUES (?, ?, ?, 1)"
-                        } else {
-                            "INSERT INTO users (userid, username, realname) VALUES (?, ?, ?)"
-                        }
-                    )
-                        .bind(msg.chat.id.0)
-                        .bind(msg.chat.username().unwrap_or(""))
-                        .bind(format!("{} {}", msg.chat.first_name().unwrap_or(""), msg.chat.last_name().unwrap_or("")))
-                        .execute(&db).await;
-                }
-                "help" => {
-                    bot.send_message(msg.chat.id, r#"Help Message"#).await?;
-                },
-                "order"|"식사순서"|"식집순서" => {
-                    let mut floor = 1;
-                    let now = time_now();
-                    if now.weekday() as u32 <= chrono::Weekday::Fri as u32 && (now.time() > NaiveTime::from_hms_opt(8, 30, 0).unwrap() && now.time() < NaiveTime::from_hms_opt(12, 0, 0).unwrap()) {
-                        floor = 0;
-                    }
-
-                    bot.send_message(msg.chat.id, format!("<b>식집순서</b>: {}", ORDERS[(*ORDERIDX.lock().unwrap())[floor]]))
-                            .parse_mode(ParseMode::Html).await?;
-                },
-                "아침"|"아침메뉴"|"아침식사" => {
-                    bot.send_message(msg.chat.id, get_menus(
-                        &get_jjam(time_now().date_naive()).await.unwrap_or_else(|e| {
-                            eprintln!("daemon_error: {e}");
-                            Vec::new()
-                        }),
-                        words[0],
-                        MealType::Breakfast
-                    ).await.map_err(|e| e.tel_err())?).parse_mode(ParseMode::Html).await?;
-                },  
-                "점심"|"점심메뉴"|"점심식사" => {
-                    bot.send_message(msg.chat.id, get_menus(&get_jjam(time_now().date_naive()).await.unwrap_or_else(|e| {
-                            eprintln!("daemon_error: {e}");
-                            Vec::new()
-                        }),
-                        words[0],
-                        MealType::Lunch
-                    ).await.map_err(|e| e.tel_err())?).parse_mode(ParseMode::Html).await?;
-                },
-                "저녁"|"저녁메뉴"|"저녁식사" => {
-                    bot.send_message(msg.chat.id, get_menus(&get_jjam(time_now().date_naive()).await.unwrap_or_else(|e| {
-                            eprintln!("daemon_error: {e}");
-                            Vec::new()
-                        }),
-                        words[0],
-                        MealType::Dinner
-                    ).await.map_err(|e| e.tel_err())?).parse_mode(ParseMode::Html).await?;
-                },
-                "낼아침"|"내일아침"|"내일아침메뉴"|"내일아침식사" => {
-                    bot.send_message(msg.chat.id, get_menus(
-                        &get_jjam((time_now() + chrono::Duration::days(1)).date_naive()).await.unwrap_or_else(|e| {
-                            eprintln!("daemon_error: {e}");
-                            Vec::new()
-                        }),
-                        words[0],
-                        MealType::Breakfast
-                    ).await.map_err(|e| e.tel_err())?).parse_mode(ParseMode::Html).await?;
-                },
-                "reserve"|"예약"|"등록" => {
-                    'errorjmp:{
-                        if words.len() < 2 {
-                            break 'errorjmp;
-                        }
-                        let when = if words.len() >= 3 {
-                            if let Ok(t) = NaiveTime::parse_from_str(&(words[2])[0..5], "%H:%M"){
-                                t.with_second(0)
-                            } else {
-                                break 'errorjmp;
-                            }
-                        } else {
-                            None
-                        };
-                        match words[1] {
-                            "아침메뉴"|"아침식사" => {
-                                add_book(msg.chat.id.0, &when.unwrap_or(HM_07_15.unwrap()).format("%H:%M").to_string(), "breakfastorder", &db).await.map_err(|e| e.tel_err())?;
-                            },
-                            "아침식집"|"아침식집순서"|"아침식사순서" => {
-                                add_book(msg.chat.id.0, &when.unwrap_or(HM_07_15.unwrap()).format("%H:%M").to_string(), "breakfast", &db).await.map_err(|e| e.tel_err())?;
-                            },
-                            "점심메뉴"|"점심식사" => {
-                                add_book(msg.chat.id.0, &when.unwrap_or(HM_11_00.unwrap()).format("%H:%M").to_string(), "lunch", &db).await.map_err(|e| e.tel_err())?;
-                            },
-                            "점심식집"|"점심식집순서"|"점심식사순서" => {
-                                add_book(msg.chat.id.0, &when.unwrap_or(HM_11_00.unwrap()).format("%H:%M").to_string(), "lunchorder", &db).await.map_err(|e| e.tel_err())?;
-                            },
-                            "저녁메뉴"|"저녁식사" => {
-                                add_book(msg.chat.id.0, &when.unwrap_or(HM_17_00.unwrap()).format("%H:%M").to_string(), "dinner", &db).await.map_err(|e| e.tel_err())?;
-                            },
-                            "저녁식집"|"저녁식집순서"|"저녁식사순서" => {
-                                add_book(msg.chat.id.0, &when.unwrap_or(HM_17_00.unwrap()).format("%H:%M").to_string(), "dinnerorder", &db).await.map_err(|e| e.tel_err())?;
-                            },
-                            "익일아침메뉴"|"익일아침식사" => {
-                                add_book(msg.chat.id.0, &when.unwrap_or(HM_20_00.unwrap()).format("%H:%M").to_string(), "nextbreakfast", &db).await.map_err(|e| e.tel_err())?;
-                            },
-                            &_ => break 'errorjmp,
-                        }
-                        let _ = bot.send_message(msg.chat.id, if words.len() == 2 {
-                            format!("{} 예약이 완료되었습니다.", words[1])
-                        } else {
-                            format!("{}에 {} 예약이 완료되었습니다.", words[2], words[1])
-                        }).await;
-                        break 'done;
-                    }
-                    let _ = bot.send_message(msg.chat.id, r#"
-사용법은 다음과 같습니다.
-예약 <종류> <시간(생략가능)>
-예약 점심메뉴 11:00
-<종류> 목록: 아침메뉴, 점심메뉴, 저녁메뉴, 익일아침메뉴
-<시간> 입력시 시:분 형태로 입력해주십시오.
-                    "#).await;
-
-                },
-               "delete"|"삭제"|"제거" => {
-                    'errorjmp:{
-                        if words.len() < 2 {
-                            break 'errorjmp;
-                        }
-                        let success = match words[1] {
-                            "아침메뉴"|"아침식사" => del_book(msg.chat.id.0, "breakfast", &db).await.is_ok(),
-                            "아침식집"|"아침식집순서"|"아침식사순서" => del_book(msg.chat.id.0, "breakfastorder", &db).await.is_ok(),
-                            "점심메뉴"|"점심식사" => del_book(msg.chat.id.0, "lunch", &db).await.is_ok(),
-                            "점심식집"|"점심식집순서"|"점심식사순서" => del_book(msg.chat.id.0, "lunchorder", &db).await.is_ok(),
-                            "저녁메뉴"|"저녁식사" => del_book(msg.chat.id.0, "dinner", &db).await.is_ok(),
-                            "저녁식집"|"저녁식집순서"|"저녁식사순서" => del_book(msg.chat.id.0, "dinnerorder", &db).await.is_ok(),
-                            "익일아침메뉴"|"익일아침식사" => del_book(msg.chat.id.0, "nextbreakfast", &db).await.is_ok(),
-                            &_ => break 'errorjmp,
-                        };
-                        if !success {
-                            break 'errorjmp;
-                        }
-                        let _ = bot.send_message(msg.chat.id, format!("{} 예약이 취소되었습니다.", words[1])).await;
-                        break 'done;
-                    }
-                    let _ = bot.send_message(msg.chat.id, r#"
-사용법은 다음과 같습니다.
-예약 <종류> <시간(생략가능)>
-예약 점심메뉴
-<종류> 목록: 아침메뉴, 점심메뉴, 저녁메뉴, 익일아침메뉴
-                    "#).await;
+    Some(best.0)
+}
 
-                },
-                "admin"|"관리"|"설정" => {
-                    if let Ok(v) = sqlx::query("SELECT 1 from users WHERE userid=? AND admin=1;")
-                      .bind(msg.chat.id.0)
-                      .fetch_one(&db)
-                      .await {
-                        {
-                            let exists: u8 = v.get(0);
-                            if exists != 1 {
-                                break 'done;
-                            }
-                        }
-                        if words.len() < 2 {
-                            let _ = bot.send_message(msg.chat.id, r#"ERROR
-                            "#).await;
-                            break 'done;
-                        }
-                        let mut reply: String = String::new();
-                        'adm_done:{
-                            'adm_error:{
-                                match words[1] {
-                                    "changeorder"|"식사순서변경"|"식집순서변경"|"식사순서"|"식집순서" => {
-                                        if words.len() < 4 {
-                                            reply = format!("This needs 2 more argumemts (floor, changes)");
-                                            break 'adm_error
-                                        }
-                                        let ordr = update_orderidx(match words[2].parse::<u32>() {
-                                            Ok(floor) => (floor - 1) as usize,
-                                            _ => break 'adm_error,
-                                        },
-                                        match words[3].parse::<i32>() {
-                                            Ok(v) => v,
-                                            _ => break 'adm_error,
-                                        });
-                                        reply = format!("Now {}", ORDERS[ordr]);
-                                        break 'adm_done;
-                                    },
-                                    _ => {},
-                                }
-                            }
-                            let idx = [update_orderidx(0, 0), update_orderidx(1, 0)];
-                            let orders = [ORDERS[idx[0]], ORDERS[idx[1]]];
-                            let _ = bot.send_message(msg.chat.id, format!("ERROR: {} \n사용법: {} {} <층수> <차이>\n1층: {}\n2층: {}", reply, words[0], words[1], orders[0], orders[1])).await;
-                            break 'done;
-                        }
-                        let _ = bot.send_message(msg.chat.id, format!("{}", reply)).await;
-                        break 'done;
-                    }
-                },
-                "time"|"date"|"datetime"|"시간" => {
-                    let _ = bot.send_message(msg.chat.id, (time_now()).format("%Y-%m-%d %H:%M:%S").to_string()).await;
-                },
+async fn user_profile(id: i64, storage: &dyn Storage) -> Profile {
+    storage.profile(id).await.unwrap_or_else(|e| {
+        eprintln!("profile_error: {e}");
+        Profile::default()
+    })
+}
 
-                &_ => {},
-            }
-        }
-        println!("{}", msg.chat.id);
-        Ok(())
-    }).await;
+// 연결, 설정은 AppState 하나를 모든 메시지가 같이 씀
+async fn tg_reply_daemon(state: Arc<AppState>) -> Result<(), ShowError> {
+    let bot = Bot::from_env();
+    // 입력창 자동완성에 뜨는 명령 목록
+    if let Err(e) = bot.set_my_commands(Command::bot_commands()).await {
+        eprintln!("daemon_error: {e}");
+    }
+    Dispatcher::builder(bot, commands::schema())
+        .dependencies(dptree::deps![state])
+        .enable_ctrlc_handler()
+        .build()
+        .dispatch()
+        .await;
     Ok(())
 }
 
-async fn get_last_order(floor: usize, now: NaiveDateTime, db: &Pool<Sqlite>) -> Result<u32, ShowError> {
-    match sqlx::query("SELECT orderidx FROM orders WHERE floor=? ORDER BY datestime DESC LIMIT 1;")
-    .bind(floor as u32)
-    .fetch_one(db).await {
-        Ok(row) => {
-            Ok(row.get(0))
-        },
-        Err(e) => {
-            eprintln!("Warning: {}\n order: {}", e, ORDERS[0]);
-            sqlx::query("INSERT INTO orders (datestime, floor, ordertxt, orderidx) VALUES (?, ?, ?, ?);")
-                .bind(now.format("%Y-%m-%d %H:%M").to_string())
-                .bind(0)
-                .bind(ORDERS[0])
-                .bind(0)
-                .execute(db)
-                .await?;
-
+// 기록이 없으면 첫 순서로 시작
+async fn get_last_order(floor: usize, now: NaiveDateTime, storage: &dyn Storage) -> Result<usize, ShowError> {
+    match storage.last_order(floor).await? {
+        Some(idx) => Ok(idx),
+        None => {
+            eprintln!("Warning: no order for floor {}\n order: {}", floor, ORDERS[0]);
+            storage.insert_order(now, floor, 0).await?;
             Ok(0)
         }
     }
 }
 
-async fn insert_order(floor: usize, now: NaiveDateTime, db: &Pool<Sqlite>) -> Result<(), ShowError> {
+async fn insert_order(floor: usize, now: NaiveDateTime, storage: &dyn Storage) -> Result<(), ShowError> {
     let v = update_orderidx(floor, 1);
-    sqlx::query("INSERT INTO orders (datestime, floor, ordertxt, orderidx) VALUES (?, ?, ?, ?);")
-        .bind(now.format("%Y-%m-%d %H:%M").to_string())
-        .bind(floor as u32)
-        .bind(ORDERS[v])
-        .bind(v as u32)
-        .execute(db)
+    storage.insert_order(now, floor, v).await
+}
+
+async fn send_tg(client: &reqwest::Client, tg_token: &str, userid: i64, msg: &str) -> Result<(), ShowError> {
+    client
+        .get(format!("https://api.telegram.org/bot{}/sendMessage", tg_token))
+        .query(&[("chat_id", userid.to_string().as_str()), ("parse_mode", "HTML"), ("text", msg)])
+        .header(reqwest::header::USER_AGENT, USER_AGENT_FIREFOX)
+        .send()
         .await?;
     Ok(())
 }
 
-async fn jjam_alarm() -> Result<(), ShowError>{
+async fn jjam_alarm(state: Arc<AppState>) -> Result<(), ShowError>{
     let mut now = time_now();
-    let orderdb;
+    let storage = state.storage.as_ref();
     
     println!("{:?}", update_orderidx(0, 0));
     { // Init Orders DB
-        if !Sqlite::database_exists("sqlite://data/orders.sqlite?mode=ro").await.unwrap_or(false) {
-            match Sqlite::create_database("sqlite://data/orders.sqlite").await {
-                Ok(_) => {
-                    println!("Order Log Database created");
-                    orderdb = SqlitePool::connect("sqlite://data/orders.sqlite?mode=rw").await?;
-                    orderdb.execute(r#"CREATE TABLE orders(
-                        id INTEGER PRIMARY KEY,
-                        datestime TEXT,
-                        floor INTEGER CHECK(orderidx IN (0, 1)),
-                        ordertxt TEXT,
-                        orderidx INTEGER CHECK(orderidx IN (0, 1, 2))
-                    );"#).await?;
-                },
-                Err(e) => {
-                    panic!("Setup orders.sqlite failed: {}", e);
-                }
-            }
-        } else {
-            orderdb = SqlitePool::connect("sqlite://data/orders.sqlite?mode=rw").await?;
-        }
-
-        let mut ordridx = ORDERIDX.lock().unwrap();
-        (*ordridx)[0] = get_last_order(0, now.naive_utc(), &orderdb).await? as usize;
-        (*ordridx)[1] = get_last_order(1, now.naive_utc(), &orderdb).await? as usize;
-        drop(ordridx);
+        let last_orders = [
+            get_last_order(0, now.naive_utc(), storage).await?,
+            get_last_order(1, now.naive_utc(), storage).await?,
+        ];
+        *ORDERIDX.lock().unwrap() = last_orders;
     }
 
     let client = reqwest::Client::new();
-    let tg_token = std::env::var(TG_TOKEN).unwrap();
-    let mut jjams = get_jjam(time_now().date_naive()).await.unwrap_or_else(|e| {
-        eprintln!("daemon_error: {e}");
-        Vec::new()
-    });
-    let timerdb = SqlitePool::connect("sqlite://data/users.sqlite?mode=ro").await?;
+    let tg_token = &state.config.tg_token;
+    let mut jjams = alarm_jjams(&state, time_now().date_naive()).await;
     loop {
         now = time_now().with_second(0).unwrap().with_nanosecond(0).unwrap();
 
         match Some(now.time()) {
             HM_00_00 => {
-                jjams = get_jjam(now.date_naive()).await.unwrap_or_else(|e| {
-                    eprintln!("daemon_error: {e}");
-                    Vec::new()
-                });
+                jjams = alarm_jjams(&state, now.date_naive()).await;
             },
-            HM_08_00 => if now.weekday() != chrono::Weekday::Sat {
-                insert_order(1, now.naive_utc(), &orderdb).await?;
+            HM_08_00 if now.weekday() != chrono::Weekday::Sat => {
+                insert_order(1, now.naive_utc(), storage).await?;
             },
-            HM_11_00 => if now.weekday() == chrono::Weekday::Sat {
-                insert_order(1, now.naive_utc(), &orderdb).await?;
+            HM_11_00 if now.weekday() == chrono::Weekday::Sat => {
+                insert_order(1, now.naive_utc(), storage).await?;
             },
             HM_12_00 => if now.weekday() as u32 <= chrono::Weekday::Fri as u32 {
-                insert_order(0, now.naive_utc(), &orderdb).await?;
+                insert_order(0, now.naive_utc(), storage).await?;
             } else if now.weekday() == chrono::Weekday::Sun {
-                insert_order(1, now.naive_utc(), &orderdb).await?;
+                insert_order(1, now.naive_utc(), storage).await?;
             },
             HM_18_00 => {
-                insert_order(1, now.naive_utc(), &orderdb).await?
+                insert_order(1, now.naive_utc(), storage).await?
             },
             _ => {},
         }
 
-        let rows = sqlx::query("SELECT * FROM timer WHERE timewhen=?")
-            .bind(now.format("%H:%M").to_string()).fetch_all(&timerdb).await?;
-
-        for r in rows {
-            let userid = r.get::<i64, &str>("userid");
-            let msgtype = r.get::<&str, &str>("what");
-            let msg = match msgtype {
-                "breakfast" => get_menus(&jjams, "아침 메뉴", MealType::Breakfast).await?,
-                "breakfastorder" => format!("식사순서: {}", ORDERS[(*ORDERIDX.lock().unwrap())[1]]),
-                "lunch" => if now.weekday() == chrono::Weekday::Sat { continue; } else {
-                    get_menus(&jjams, "점심 메뉴", MealType::Lunch).await?
-                },
-                "lunchorder" => if now.weekday() == chrono::Weekday::Sat { continue; } else {
-                    format!("식사순서: {}", ORDERS[(*ORDERIDX.lock().unwrap())[
-                        if now.weekday() as u32 > chrono::Weekday::Fri as u32 {
-                            1
-                        } else {
-                            0
-                        }
-                    ]])
-                },
-                "dinner" => get_menus(&jjams, "저녁 메뉴", MealType::Dinner).await?,
-                "dinnerorder" => format!("식사순서: {}", ORDERS[(*ORDERIDX.lock().unwrap())[1]]),
-                "nextbreakfast" => get_menus(&jjams, "내일 아침 메뉴", MealType::Breakfast).await?,
-                "nextbreakfastorder" => format!("식사순서: {}", ORDERS[(*ORDERIDX.lock().unwrap())[1]]),
-                _ => continue,
-            };
-
-            let _ = client
-                .get(format!("https://api.telegram.org/bot{}/sendMessage?chat_id={}&parse_mode=HTML&text={}", tg_token, userid, msg))
-                .header(reqwest::header::USER_AGENT, USER_AGENT_FIREFOX)
-                .send()
-            .await?;
+        // 한 사람에게 못 보내도 (차단, 일시적인 네트워크 오류 등) 알람은 계속 돌아야 함
+        let messages = alarm_messages(storage, now, &jjams).await.unwrap_or_else(|e| {
+            eprintln!("alarm_error: {e}");
+            Vec::new()
+        });
+        for (userid, msg) in messages {
+            if let Err(e) = send_tg(&client, tg_token, userid, &msg).await {
+                eprintln!("alarm_error({}): {}", userid, e);
+            }
         }
         let duration_time = (time_now().duration_trunc(Duration::minutes(1)).unwrap() + Duration::minutes(1)).signed_duration_since(time_now());
         assert!(duration_time > Duration::zero(), "duration time is {duration_time}");
@@ -647,163 +566,709 @@ async fn jjam_alarm() -> Result<(), ShowError>{
     }
 }
 
-async fn jjam_poll() -> Result<(), ShowError> {
-    let mut jjam_count: i32;
-    let mut jjam_dbcnt: i32 = 0;
-    let mut dburi: (Option<String>, Option<String>) = jjamdb_path("rw").await;
-    let mut db: sqlx::Pool<Sqlite>;
-    let jjam_token = std::env::var(JJAM_TOKEN).unwrap();
-    let unit_code = std::env::var(UNIT_CODE).unwrap();
-
-    // 식단 영역과 칼로리 영역 관리가 엉망이라, 일부 경우에 대해서 수동으로 위치를 서로 바꿈
-    // 예 1: (meal="320kcal", calorie="밤양갱") -> ("밤양갱", "320")
-    // 예 2: (meal="", calorie="밤양갱") -> ("밤양갱", "")
-    let process_calorie = |meal: &str, calorie: &str| -> (String, String) {
-        if !calorie.ends_with("kcal") {
-            if meal.ends_with("kcal") {
-                (calorie.to_string(), (&meal[0..meal.len() - 4]).to_string())
-            } else {
-                (calorie.to_string(), String::new())
+// 알람에 쓸 부대별 (오늘, 내일) 식단. 키는 (부대, 며칠 뒤)
+async fn alarm_jjams(state: &AppState, today: NaiveDate) -> HashMap<(String, i64), Vec<JjamRow>> {
+    let mut jjams = HashMap::new();
+    for unit in unit_codes() {
+        for days in [0, 1] {
+            let rows = get_jjam(state, &unit, today + Duration::days(days)).await.unwrap_or_else(|e| {
+                eprintln!("daemon_error: {e}");
+                Vec::new()
+            });
+            jjams.insert((unit.clone(), days), rows);
         }
-        } else {
-            (meal.to_string(), (&calorie[0 .. calorie.len() - 4]).to_string())
+    }
+    jjams
+}
+
+// now(분 단위)에 보낼 예약 알림
+async fn alarm_messages(storage: &dyn Storage, now: chrono::DateTime<chrono::Utc>, jjams: &HashMap<(String, i64), Vec<JjamRow>>) -> Result<Vec<(i64, String)>, ShowError> {
+    let mut messages = Vec::new();
+    let no_jjam = Vec::new();
+    for book in storage.books_at(&now.format("%H:%M").to_string()).await? {
+        // 지금은 없는 종류 (예전 brunch 등)
+        let Some(kind) = kinds::by_what(&book.what) else {
+            continue;
+        };
+        if kind.skip_saturday && now.weekday() == chrono::Weekday::Sat {
+            continue;
         }
-    };
+        let msg = match kind.content {
+            Content::Menu => {
+                let jjams = book.unit.clone()
+                    .and_then(|unit| jjams.get(&(unit, kind.days)))
+                    .or_else(|| jjams.get(&(default_unit(), kind.days)))
+                    .unwrap_or(&no_jjam);
+                let profile = user_profile(book.userid, storage).await;
+                get_menus(jjams, &kind.menu_title(), kind.meal, &profile).await?
+            },
+            Content::Order { weekday_floor } => {
+                let floor = if now.weekday() as u32 > chrono::Weekday::Fri as u32 { 1 } else { weekday_floor };
+                format!("식사순서: {}", ORDERS[(*ORDERIDX.lock().unwrap())[floor]])
+            },
+        };
+        messages.push((book.userid, msg));
+    }
+    Ok(messages)
+}
 
+// 식단 영역과 칼로리 영역 관리가 엉망이라, 일부 경우에 대해서 수동으로 위치를 서로 바꿈
+// 예 1: (meal="320kcal", calorie="밤양갱") -> ("밤양갱", Some(320.0))
+// 예 2: (meal="", calorie="밤양갱") -> ("밤양갱", None)
+// 예 3: (meal="밤양갱", calorie="미정") -> ("밤양갱", None)
+fn process_calorie(meal: &str, calorie: &str) -> (String, Option<f64>) {
+    if let Some(kcal) = parse_kcal(calorie) {
+        (meal.to_string(), Some(kcal))
+    } else if let Some(kcal) = parse_kcal(meal) {
+        (calorie.to_string(), Some(kcal))
+    } else if meal.trim().is_empty() {
+        (calorie.to_string(), None)
+    } else {
+        (meal.to_string(), None)
+    }
+}
 
-    let client = reqwest::Client::new();
-    loop{
-        let res = client
-            .get(format!("https://openapi.mnd.go.kr/{}/json/DS_TB_MNDT_DATEBYMLSVC_{}/1/1", jjam_token, unit_code))
-            .header(reqwest::header::USER_AGENT, USER_AGENT_FIREFOX)
-            .send()
-            .await?;
-
-            jjam_count = serde_json::from_str::<JjamDataShell>(&res.text().await?)
-                .map_err(|e| e.to_string())?
-                .DS_TB_MNDT_DATEBYMLSVC_7021.list_total_count;
-
-        'db_init:{
-            if let Some(first) = &dburi.0 {
-                println!("DB PATH: {}", first);
-                db = SqlitePool::connect(&first).await?;
-                let row = sqlx::query("SELECT COUNT(*) FROM jjam")
-                    .fetch_one(&db)
-                    .await?;
-                jjam_dbcnt = row.get(0);
-                println!("{}", jjam_dbcnt);
-            }
+// 재시도 간격: 5초부터 두 배씩 늘려서 최대 10분, 여러 부대가 동시에 몰리지 않게 최대 절반만큼 흔들어줌
+fn backoff_delay(attempt: u32) -> std::time::Duration {
+    let base = std::time::Duration::from_secs(5)
+        .saturating_mul(1 << attempt.min(16))
+        .min(std::time::Duration::from_secs(600));
+    let seed = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    base + base.mul_f64((seed % 1000) as f64 / 2000.0)
+}
 
-            if jjam_count != jjam_dbcnt {
-                dburi.1 = dburi.0.clone();
-                dburi.0 = Some(format!("sqlite://data/jjam-{}.sqlite?mode=rw", time_now().date_naive()));
-                if !Sqlite::database_exists(&dburi.0.clone().unwrap()).await.unwrap_or(false) {
-                    match Sqlite::create_database(&dburi.0.clone().unwrap()).await {
-                        Ok(_) => {
-                            println!("Database created");
-                            db = SqlitePool::connect(&dburi.0.clone().unwrap()).await?;
-                            db.execute(r#"CREATE TABLE jjam(
-                                id INTEGER PRIMARY KEY,
-                                dates TEXT,
-                                brst TEXT,
-                                brst_cal TEXT,
-                                lunc TEXT,
-                                lunc_cal TEXT,
-                                dinr TEXT,
-                                dinr_cal TEXT,
-                                adspcfd TEXT,
-                                adspcfd_cal TEXT,
-                                sum_cal TEXT
-                            );"#).await?;
-                        },
-                        Err(e) => {
-                            eprintln!("Error creating database: {}", e);
-                            return Ok(());
-                        },
-                    }
-                } else {
-                    println!("Database already exists");
-                    break 'db_init;
+fn record_poll<T>(unit: &str, result: &Result<T, ShowError>) {
+    let mut health = POLL_HEALTH.lock().unwrap();
+    let entry = health.entry(unit.to_string()).or_default();
+    let now = time_now().naive_utc();
+    entry.last_attempt = Some(now);
+    match result {
+        Ok(_) => {
+            entry.last_success = Some(now);
+            entry.failures = 0;
+            entry.last_error = None;
+        },
+        Err(e) => {
+            entry.failures += 1;
+            entry.last_error = Some(e.to_string());
+        },
+    }
+}
+
+fn poll_status() -> String {
+    let health = POLL_HEALTH.lock().unwrap().clone();
+    let mut reply = String::from("<b>식단 수집 상태</b>");
+    if let Some(next) = POLL_SCHEDULE.next_after(time_now().naive_utc()) {
+        reply = format!("{}\n다음 수집: {} ({})", reply, next.format("%m-%d %H:%M"), *POLL_SCHEDULE);
+    }
+    for unit in unit_codes() {
+        let fmt = |t: Option<NaiveDateTime>| t.map(|t| t.format("%m-%d %H:%M").to_string()).unwrap_or("-".to_string());
+        match health.get(&unit) {
+            Some(h) => {
+                reply = format!("{}\n\n{} {}\n마지막 시도: {}\n마지막 성공: {}",
+                    reply, unit, if h.failures == 0 { "정상" } else { "실패" }, fmt(h.last_attempt), fmt(h.last_success));
+                if let Some(e) = &h.last_error {
+                    reply = format!("{}\n연속 실패 {}회: {}", reply, h.failures, e);
                 }
-                db.execute("PRAGMA journal_mode=WAL").await?;
+            },
+            None => reply = format!("{}\n\n{} 아직 수집 전", reply, unit),
+        }
+    }
+    reply
+}
 
-                let json = client
-                .get(format!("https://openapi.mnd.go.kr/{}/json/DS_TB_MNDT_DATEBYMLSVC_{}/1/{}", jjam_token, unit_code, jjam_count))
-                .header(reqwest::header::USER_AGENT, USER_AGENT_FIREFOX)
-                .send()
-                .await?
-                .text()
-                .await?;
+// API에서 받은 행을 저장할 형태로 정리
+fn normalize_jjam(raw: RawJjamRow) -> JjamRow {
+    let (brst, brst_cal) = process_calorie(&raw.brst, &raw.brst_cal);
+    let (lunc, lunc_cal) = process_calorie(&raw.lunc, &raw.lunc_cal);
+    let (dinr, dinr_cal) = process_calorie(&raw.dinr, &raw.dinr_cal);
+    let (adspcfd, adspcfd_cal) = process_calorie(&raw.adspcfd, &raw.adspcfd_cal);
+    JjamRow {
+        dates: raw.dates.chars().take(10).collect(),
+        brst,
+        brst_cal,
+        lunc,
+        lunc_cal,
+        dinr,
+        dinr_cal,
+        adspcfd,
+        adspcfd_cal,
+        sum_cal: parse_kcal(&raw.sum_cal),
+    }
+}
 
-                let jjam;
+fn group_by_date(rows: Vec<JjamRow>) -> BTreeMap<String, Vec<JjamRow>> {
+    let mut dates: BTreeMap<String, Vec<JjamRow>> = BTreeMap::new();
+    for meal in rows {
+        dates.entry(meal.dates.clone()).or_default().push(meal);
+    }
+    dates
+}
 
-                {
-                    let jjamshell: JjamDataShell = serde_json::from_str(&json).map_err(|e| e.to_string())?;
-                    jjam = jjamshell.DS_TB_MNDT_DATEBYMLSVC_7021;
-                }
-                let row = jjam.row.borrow();
-
-                for meal in &mut row.iter() {
-                    let mut meal = meal.clone();
-                    println!("{:?}", meal);
-                    meal.dates = if meal.dates.len() < 10 {
-                        meal.dates.clone()
-                    } else {
-                        (&meal.dates.clone()[0..10]).to_string()
-                    };
-                    (meal.brst, meal.brst_cal) = process_calorie(&meal.brst, &meal.brst_cal);
-                    (meal.lunc, meal.lunc_cal) = process_calorie(&meal.lunc, &meal.lunc_cal);
-                    (meal.dinr, meal.dinr_cal) = process_calorie(&meal.dinr, &meal.dinr_cal);
-                    (meal.adspcfd, meal.adspcfd_cal) = process_calorie(&meal.adspcfd, &meal.adspcfd_cal);
-                    meal.sum_cal = if meal.sum_cal.len() < 4 {
-                        meal.sum_cal.clone()
-                    } else {
-                            (&meal.sum_cal.clone()[0 .. meal.sum_cal.len() - 4]).to_string()
-                    };
-
-
-                    sqlx::query("INSERT INTO jjam (dates, brst, brst_cal, lunc, lunc_cal, dinr, dinr_cal, adspcfd, adspcfd_cal, sum_cal) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")
-                        .bind(&meal.dates)
-                        .bind(&meal.brst)
-                        .bind(&meal.brst_cal)
-                        .bind(&meal.lunc)
-                        .bind(&meal.lunc_cal)
-                        .bind(&meal.dinr)
-                        .bind(&meal.dinr_cal)
-                        .bind(&meal.adspcfd)
-                        .bind(&meal.adspcfd_cal)
-                        .bind(&meal.sum_cal)
-                        .execute(&db).await?;
+// 하루치 식단의 내용 해시 (FNV-1a), 빌드가 바뀌어도 값이 같아야 해서 DefaultHasher 대신 직접 계산
+fn jjam_hash(rows: &[JjamRow]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for meal in rows {
+        let kcal = |v: Option<f64>| v.map(|k| k.to_string()).unwrap_or_default();
+        for field in [meal.brst.clone(), kcal(meal.brst_cal), meal.lunc.clone(), kcal(meal.lunc_cal), meal.dinr.clone(), kcal(meal.dinr_cal), meal.adspcfd.clone(), kcal(meal.adspcfd_cal), kcal(meal.sum_cal)] {
+            for byte in field.bytes().chain([0x1f]) {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+        hash ^= 0x1e;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+// 받은 날짜의 식단을 통째로 바꿔 넣음 (나중에 고쳐 올라온 식단이 실제로 나온 식단)
+async fn archive_jjam(db: &Pool<Sqlite>, unit: &str, dates: &BTreeMap<String, Vec<JjamRow>>) -> Result<(), ShowError> {
+    let seen = time_now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let mut tx = db.begin().await?;
+    for (date, rows) in dates {
+        sqlx::query("DELETE FROM archive WHERE unit=? AND dates=?").bind(unit).bind(date).execute(&mut *tx).await?;
+        for meal in [MealType::Breakfast, MealType::Lunch, MealType::Dinner, MealType::Snack] {
+            for dish in meal.dishes(rows) {
+                sqlx::query("INSERT INTO archive (unit, dates, meal, name, allergens, kcal, seen) VALUES (?, ?, ?, ?, ?, ?, ?)")
+                    .bind(unit)
+                    .bind(date)
+                    .bind(meal.column())
+                    .bind(&dish.name)
+                    .bind(dish.allergens_str())
+                    .bind(dish.kcal)
+                    .bind(&seen)
+                    .execute(&mut *tx).await?;
+            }
+        }
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+// 검색어의 \, %, _ 를 글자 그대로 찾도록 (쿼리에 ESCAPE '\' 를 같이 씀)
+fn escape_like(keyword: &str) -> String {
+    keyword.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+// 언제 <요리>: 마지막으로 나온 날, 다음에 나올 날, 최근 1년 월별 횟수
+async fn dish_history(state: &AppState, unit: &str, keyword: &str) -> Result<String, ShowError> {
+    let db = &state.db;
+    let today = time_now().date_naive();
+    let pattern = format!("%{}%", escape_like(keyword));
+    let last = sqlx::query("SELECT dates, meal, name FROM archive WHERE unit=? AND name LIKE ? ESCAPE '\\' AND dates<=? ORDER BY dates DESC, id LIMIT 1")
+        .bind(unit).bind(&pattern).bind(today.format("%Y-%m-%d").to_string())
+        .fetch_optional(db).await;
+    let next = sqlx::query("SELECT dates, meal, name FROM archive WHERE unit=? AND name LIKE ? ESCAPE '\\' AND dates>? ORDER BY dates, id LIMIT 1")
+        .bind(unit).bind(&pattern).bind(today.format("%Y-%m-%d").to_string())
+        .fetch_optional(db).await;
+    // 같은 끼니에 비슷한 요리가 둘 나와도 한 번으로 셈
+    let months = sqlx::query(r#"SELECT substr(dates, 1, 7) AS month, COUNT(DISTINCT dates || meal) AS served
+        FROM archive WHERE unit=? AND name LIKE ? ESCAPE '\' AND dates>=?
+        GROUP BY month ORDER BY month"#)
+        .bind(unit).bind(&pattern).bind((today - Duration::days(365)).format("%Y-%m-01").to_string())
+        .fetch_all(db).await;
+    let total = sqlx::query("SELECT MIN(dates), COUNT(DISTINCT dates || meal) FROM archive WHERE unit=? AND name LIKE ? ESCAPE '\\'")
+        .bind(unit).bind(&pattern)
+        .fetch_one(db).await;
+
+    let (last, next, months, total) = (last?, next?, months?, total?);
+    let mut reply = format!("<b>{}</b> 언제 나왔나", keyword);
+    if last.is_none() && next.is_none() {
+        return Ok(format!("{}\n기록이 없습니다.", reply));
+    }
+    let line = |r: &SqliteRow| format!("{} {} ({})",
+        r.get::<&str, _>("dates"), MealType::from_column(r.get::<&str, _>("meal")).name(), r.get::<&str, _>("name"));
+    if let Some(r) = &last {
+        let ago = NaiveDate::parse_from_str(r.get::<&str, _>("dates"), "%Y-%m-%d")
+            .map(|d| match (today - d).num_days() {
+                0 => " 오늘".to_string(),
+                days => format!(" {}일 전", days),
+            })
+            .unwrap_or_default();
+        reply = format!("{}\n마지막: {}{}", reply, line(r), ago);
+    }
+    if let Some(r) = &next {
+        reply = format!("{}\n다음: {}", reply, line(r));
+    }
+    if !months.is_empty() {
+        reply = format!("{}\n\n월별 횟수", reply);
+        for r in &months {
+            let served = r.get::<i64, _>("served");
+            reply = format!("{}\n{} {} {}회", reply, r.get::<&str, _>("month"), "▇".repeat(served.min(31) as usize), served);
+        }
+    }
+    if let Some(first) = total.get::<Option<String>, _>(0) {
+        reply = format!("{}\n\n{}부터 모두 {}회", reply, first, total.get::<i64, _>(1));
+    }
+    Ok(reply)
+}
+
+// 오늘 이후 식단에서 이름에 keyword 가 들어간 요리를 찾음
+async fn search_dishes(state: &AppState, unit: &str, keyword: &str) -> Result<String, ShowError> {
+    let keyword_lower = keyword.to_lowercase();
+    let dishes: Vec<storage::DishRow> = state.storage.dishes_from(unit, time_now().date_naive()).await?
+        .into_iter()
+        .filter(|d| d.name.to_lowercase().contains(&keyword_lower))
+        .take(20)
+        .collect();
+
+    let mut reply = format!("<b>{}</b> 검색 결과", keyword);
+    if dishes.is_empty() {
+        return Ok(format!("{}\n앞으로의 식단에 없습니다.", reply));
+    }
+    for d in dishes {
+        reply = format!("{}\n{} {} {}", reply, d.dates, MealType::from_column(&d.meal).name(), d.name);
+    }
+    Ok(reply)
+}
+
+// 오늘 이후 끼니 중 요리 칼로리 합이 limit 이하인 끼니 (칼로리를 모르는 요리가 있는 끼니는 뺌)
+async fn kcal_meals(state: &AppState, unit: &str, limit: f64) -> Result<String, ShowError> {
+    // (날짜, 끼니) 별 칼로리 합, 칼로리를 모르는 요리가 있으면 None
+    let mut meals: BTreeMap<(String, u8), Option<f64>> = BTreeMap::new();
+    for d in state.storage.dishes_from(unit, time_now().date_naive()).await? {
+        let order = match d.meal.as_str() {
+            "brst" => 0,
+            "lunc" => 1,
+            "dinr" => 2,
+            _ => continue,
+        };
+        let total = meals.entry((d.dates, order)).or_insert(Some(0.0));
+        *total = total.zip(d.kcal).map(|(sum, kcal)| sum + kcal);
+    }
+
+    let mut totals: Vec<f64> = Vec::new();
+    let mut unknown = 0;
+    let mut reply = format!("<b>{:.0}kcal 이하 끼니</b>", limit);
+    let mut found = 0;
+    for ((date, order), total) in meals {
+        let Some(total) = total else {
+            unknown += 1;
+            continue;
+        };
+        totals.push(total);
+        if total <= limit {
+            let meal = [MealType::Breakfast, MealType::Lunch, MealType::Dinner][order as usize].name();
+            reply = format!("{}\n{} {} {:.0}kcal", reply, date, meal, total);
+            found += 1;
+        }
+    }
+    if found == 0 {
+        reply = format!("{}\n앞으로의 식단에 없습니다.", reply);
+    }
+    if !totals.is_empty() {
+        reply = format!("{}\n\n끼니 평균 {:.0}kcal (최소 {:.0}, 최대 {:.0}, {}끼니)", reply,
+            totals.iter().sum::<f64>() / totals.len() as f64,
+            totals.iter().copied().fold(f64::INFINITY, f64::min),
+            totals.iter().copied().fold(0.0, f64::max),
+            totals.len());
+    }
+    if unknown > 0 {
+        reply = format!("{}\n칼로리를 모르는 요리가 있어 뺀 끼니: {}", reply, unknown);
+    }
+    Ok(reply)
+}
+
+// 건수만 비교하면 이미 올라온 식단을 고친 것을 놓치므로 수집할 때마다 새로 만들어서 전체를 받고 날짜별로 비교함
+fn unit_fetch(unit: &str) -> PagedFetch<RawJjamRow> {
+    let page_size = std::env::var(JJAM_PAGE_SIZE).ok()
+        .and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(mnd::MAX_PAGE_SIZE);
+    PagedFetch::new(&jjam_service(unit), page_size)
+}
+
+// fetch 는 부르는 쪽이 들고 있어서, 실패 후 다시 부르면 받아둔 행 다음부터 이어서 받음
+async fn poll_unit(state: &AppState, client: &MndClient, unit: &str, fetch: &mut PagedFetch<RawJjamRow>) -> Result<Vec<MenuChange>, ShowError> {
+    let mut attempt = 0;
+    loop {
+        match fetch.resume(client).await {
+            Ok(_) => break,
+            Err(MndError::NoData) => {
+                println!("{}: no data", unit);
+                return Ok(Vec::new());
+            },
+            Err(e) => {
+                attempt += 1;
+                if attempt >= JJAM_FETCH_RETRY || !e.is_transient() {
+                    return Err(e.into());
                 }
+                let delay = backoff_delay(attempt);
+                eprintln!("{}: fetch failed after {} rows ({}), retrying in {:?}", unit, fetch.fetched(), e, delay);
+                sleep(delay).await;
+            },
+        }
+    }
+    println!("{}: {} rows fetched", unit, fetch.fetched());
+    store_jjam(state, unit, fetch.rows().to_vec(), true).await
+}
+
+// 받아온(또는 파일에서 읽은) 원본 행을 정리해서 부대의 식단(menus, dishes)에 반영하고, 앞으로의 식단 중 바뀐 끼니를 돌려줌
+// prune_missing: 받은 행에 없는 날짜를 지움 (API 전체를 받은 수집만, 일부 기간만 담긴 파일 가져오기는 합치기만 함)
+async fn store_jjam(state: &AppState, unit: &str, rows: Vec<RawJjamRow>, prune_missing: bool) -> Result<Vec<MenuChange>, ShowError> {
+    let mut fetched = group_by_date(rows.into_iter().map(normalize_jjam).collect());
+    // 보관 기간(JJAM_KEEP_DAYS)이 지난 날짜는 받아도 넣지 않고 저장된 것은 지움
+    // 날짜마다 처음 받았을 때 보관함에 들어가므로 '언제' 기록은 남음
+    let keep_from = state.config.keep_days
+        .map(|days| (time_now().date_naive() - Duration::days(days)).format("%Y-%m-%d").to_string());
+    let expired = |date: &str| keep_from.as_deref().is_some_and(|from| date < from);
+    fetched.retain(|date, _| !expired(date));
+    // 비교는 답장에 쓰는 저장소 내용과 함 (저장에 실패했으면 다음 수집 때 다시 바뀐 것으로 보임)
+    let previous = match state.storage.menu_range(unit).await? {
+        Some(range) => group_by_date(state.storage.menus_from(unit, range.first).await?),
+        None => BTreeMap::new(),
+    };
+    println!("{}: {} dates stored", unit, previous.len());
+
+    let changed: Vec<&String> = fetched.keys()
+        .chain(previous.keys().filter(|date| (prune_missing && !fetched.contains_key(*date)) || expired(date)))
+        .filter(|date| fetched.get(*date).map(|rows| jjam_hash(rows)) != previous.get(*date).map(|rows| jjam_hash(rows)))
+        .collect();
+    if changed.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // 보관함은 날짜별로 덮어쓰므로 다시 해도 같음, 그래서 저장소보다 먼저 씀
+    let archive: BTreeMap<String, Vec<JjamRow>> = changed.iter()
+        .filter_map(|date| fetched.get(*date).map(|rows| ((*date).clone(), rows.clone())))
+        .collect();
+    archive_jjam(&state.db, unit, &archive).await?;
+    // 바뀐 날짜만 바꿈, 없어진 날짜는 빈 목록으로 넘겨서 지우게 함
+    let menus: BTreeMap<String, Vec<JjamRow>> = changed.iter()
+        .map(|date| ((*date).clone(), fetched.get(*date).cloned().unwrap_or_default()))
+        .collect();
+    state.storage.save_menus(unit, &menus).await?;
+    println!("{}: {} dates updated", unit, changed.len());
+
+    // 전에 받아 둔 오늘 이후 식단이 바뀐 경우만 알림 대상 (새로 올라온 날짜는 제외)
+    let today = time_now().date_naive();
+    let mut changes = Vec::new();
+    for (date, before) in &previous {
+        let Ok(day) = NaiveDate::parse_from_str(date, "%Y-%m-%d") else {
+            continue;
+        };
+        if day < today || day > today + Duration::days(JJAM_NOTIFY_DAYS) {
+            continue;
+        }
+        let after = match fetched.get(date) {
+            Some(rows) => rows.as_slice(),
+            None if prune_missing => &[],
+            None => continue,
+        };
+        for meal in [MealType::Breakfast, MealType::Lunch, MealType::Dinner, MealType::Snack] {
+            let before: Vec<String> = meal.dishes(before).iter().map(Dish::to_string).collect();
+            let after: Vec<String> = meal.dishes(after).iter().map(Dish::to_string).collect();
+            if !before.is_empty() && before != after {
+                changes.push(MenuChange { date: day, meal, before, after });
+            }
+        }
+    }
+    Ok(changes)
+}
+
+// 바뀐 끼니를 예약해 둔 사용자에게 변경 전후를 보내줌
+async fn notify_menu_change(state: &AppState, client: &reqwest::Client, unit: &str, changes: &[MenuChange]) -> Result<(), ShowError> {
+    for change in changes {
+        let kinds = kinds::for_meal(change.meal);
+        if kinds.is_empty() {
+            continue;
+        }
+        let msg = change.message();
+        for userid in state.storage.subscribers(unit, &default_unit(), &kinds).await? {
+            if let Err(e) = send_tg(client, &state.config.tg_token, userid, &msg).await {
+                eprintln!("notify_error: {e}");
             }
         }
-        let local_time = chrono::Local::now();
-        let redo_time = (chrono::Local::now() + chrono::Duration::days(1))
-            .with_hour(3).unwrap()
-            .with_minute(0).unwrap()
-            .with_second(0).unwrap();
-
-        if local_time < redo_time {
-            let duration_time = (redo_time - local_time) + chrono::Duration::hours(3);
-            sleep(duration_time.to_std().unwrap()).await;
+    }
+    Ok(())
+}
+
+// 부대의 저장된 식단에서 from 이후를 검사
+async fn inspect_menus(state: &AppState, unit: &str, from: NaiveDate) -> Result<Vec<Finding>, ShowError> {
+    let rows = state.storage.menus_from(unit, from).await?;
+    Ok(quality::inspect(&rows, from))
+}
+
+// 검사 결과를 quality 테이블에 부대별 최신 한 벌만 남김. 지난 검사와 내용이 다르면 true
+async fn store_quality(state: &AppState, unit: &str, findings: &[Finding]) -> Result<bool, ShowError> {
+    let before: Vec<String> = sqlx::query("SELECT dates, kind, detail FROM quality WHERE unit=? ORDER BY id")
+        .bind(unit)
+        .fetch_all(&state.db).await?
+        .iter()
+        .map(|r| format!("{} [{}] {}", r.get::<&str, _>(0), r.get::<&str, _>(1), r.get::<&str, _>(2)))
+        .collect();
+    let after: Vec<String> = findings.iter().map(Finding::to_string).collect();
+
+    let checked = time_now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let mut tx = state.db.begin().await?;
+    sqlx::query("DELETE FROM quality WHERE unit=?").bind(unit).execute(&mut *tx).await?;
+    for finding in findings {
+        sqlx::query("INSERT INTO quality (unit, checked, dates, kind, detail) VALUES (?, ?, ?, ?, ?)")
+            .bind(unit)
+            .bind(&checked)
+            .bind(&finding.date)
+            .bind(finding.kind)
+            .bind(&finding.detail)
+            .execute(&mut *tx).await?;
+    }
+    tx.commit().await?;
+    Ok(before != after)
+}
+
+fn quality_message(unit: &str, findings: &[Finding]) -> String {
+    if findings.is_empty() {
+        return format!("<b>식단 검사</b> {}\n문제 없음", unit);
+    }
+    let mut msg = format!("<b>식단 검사</b> {} ({}건)", unit, findings.len());
+    for finding in findings.iter().take(QUALITY_REPORT_LINES) {
+        msg = format!("{}\n{}", msg, finding);
+    }
+    if findings.len() > QUALITY_REPORT_LINES {
+        msg = format!("{}\n... 외 {}건 (관리 품질 로 전체 확인)", msg, findings.len() - QUALITY_REPORT_LINES);
+    }
+    msg
+}
+
+// 수집 뒤 검사해서 저장하고, 결과가 바뀌었을 때만 관리자에게 요약을 보냄 (같은 문제로 매일 울리지 않도록)
+async fn report_quality(state: &AppState, client: &reqwest::Client, unit: &str) -> Result<(), ShowError> {
+    let findings = inspect_menus(state, unit, time_now().date_naive()).await?;
+    if !store_quality(state, unit, &findings).await? {
+        return Ok(());
+    }
+    println!("{}: {} quality findings", unit, findings.len());
+
+    let msg = quality_message(unit, &findings);
+    for userid in state.storage.admins().await? {
+        if let Err(e) = send_tg(client, &state.config.tg_token, userid, &msg).await {
+            eprintln!("notify_error: {e}");
         }
     }
+    Ok(())
+}
+
+// 관리 품질: 부대별 마지막 검사 결과
+async fn list_quality(state: &AppState) -> Result<String, ShowError> {
+    let rows = sqlx::query("SELECT unit, checked, dates, kind, detail FROM quality ORDER BY unit, id")
+        .fetch_all(&state.db).await?;
+
+    let mut reply = String::new();
+    for unit in unit_codes() {
+        let findings: Vec<Finding> = rows.iter()
+            .filter(|r| r.get::<&str, _>("unit") == unit)
+            .map(|r| Finding {
+                date: r.get::<String, _>("dates"),
+                kind: quality::kind(r.get::<&str, _>("kind")),
+                detail: r.get::<String, _>("detail"),
+            })
+            .collect();
+        reply = format!("{}{}\n\n", reply, quality_message(&unit, &findings));
+    }
+    Ok(reply.trim_end().to_string())
+}
+
+// 수집 실패는 여기서 끝내고 밖으로 내보내지 않음 (select!로 묶인 알람, 텔레그램 데몬까지 같이 죽지 않도록)
+async fn jjam_poll(state: Arc<AppState>) -> Result<(), ShowError> {
+    let client = MndClient::from_env(&state.config.jjam_token);
+    let tg_client = reqwest::Client::new();
+    loop{
+        for unit in unit_codes() {
+            // 부대마다 한 번 만들어서 다시 시도할 때도 같이 씀 (1페이지부터 다시 받지 않게)
+            let mut fetch = unit_fetch(&unit);
+            let mut attempt = 0;
+            loop {
+                let result = poll_unit(&state, &client, &unit, &mut fetch).await;
+                record_poll(&unit, &result);
+                let e = match result {
+                    Ok(changes) => {
+                        if let Err(e) = notify_menu_change(&state, &tg_client, &unit, &changes).await {
+                            eprintln!("notify_error({}): {}", unit, e);
+                        }
+                        if let Err(e) = report_quality(&state, &tg_client, &unit).await {
+                            eprintln!("quality_error({}): {}", unit, e);
+                        }
+                        break;
+                    },
+                    Err(e) => e,
+                };
+                attempt += 1;
+                eprintln!("poll_error({}): {}", unit, e);
+                // 재시도해도 안 되는 오류면 할당량만 쓰므로 바로 실패로 두고 다음 정기 수집까지 기존 데이터로 버팀
+                if attempt >= JJAM_POLL_RETRY || !e.is_transient() {
+                    break;
+                }
+                sleep(backoff_delay(attempt)).await;
+            }
+        }
+
+        // 시작할 때 한 번 받고, 그 뒤로는 JJAM_POLL_SCHEDULE 시각마다
+        let now = time_now().naive_utc();
+        let Some(next) = POLL_SCHEDULE.next_after(now) else {
+            eprintln!("poll_error: no next poll time for {}", *POLL_SCHEDULE);
+            return Ok(());
+        };
+        println!("next poll at {}", next.format("%Y-%m-%d %H:%M"));
+        sleep((next - now).to_std().unwrap_or_default()).await;
+    }
 }
 
 #[tokio::main]
 async fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    // 백업 복원은 DB 를 열기 전에 (봇을 멈추고 실행): jjambot restore <백업 이름>
+    if args.get(1).map(String::as_str) == Some("restore") {
+        let Some(name) = args.get(2) else {
+            eprintln!("usage: {} restore <backup name> (stop the bot first)\nbackups: {}", args[0], backup::list().join(", "));
+            std::process::exit(2);
+        };
+        match backup::restore(name).await {
+            Ok(before) => println!("restored: {} (previous files in {})", name, before),
+            Err(e) => {
+                eprintln!("restore_error: {e}");
+                std::process::exit(1);
+            },
+        }
+        return;
+    }
+    // 봇과 쓰기 명령은 잠금을 잡고 시작해서 서로, 그리고 restore 와 같이 돌지 않게 함
+    // (check, menu 는 읽기만 하므로 봇이 돌 때도 씀)
+    let _lock = match args.get(1).map(String::as_str) {
+        Some("check" | "menu") => None,
+        _ => match backup::lock() {
+            Ok(lock) => Some(lock),
+            Err(e) => {
+                eprintln!("lock_error: {e}");
+                std::process::exit(1);
+            },
+        },
+    };
+    // 스키마 마이그레이션과 예전 파일 가져오기는 어떤 명령이든 먼저
+    let state = match AppState::init().await {
+        Ok(state) => Arc::new(state),
+        Err(e) => {
+            eprintln!("db_error: {}", e);
+            std::process::exit(1);
+        },
+    };
+    match args.get(1).map(String::as_str) {
+        // 오프라인 반입: jjambot import <부대코드> <파일.json|파일.csv>
+        Some("import") => {
+            if args.len() < 4 {
+                eprintln!("usage: {} import <unit code> <file.json|file.csv>", args[0]);
+                std::process::exit(2);
+            }
+            if let Err(e) = import::import_file(&state, &args[2], &args[3]).await {
+                eprintln!("import_error: {e}");
+                std::process::exit(1);
+            }
+            return;
+        },
+        // 지금 백업: jjambot backup
+        Some("backup") => {
+            match backup::backup(&state).await {
+                Ok(name) => println!("{}/{}", backup::BACKUP_DIR, name),
+                Err(e) => {
+                    eprintln!("backup_error: {e}");
+                    std::process::exit(1);
+                },
+            }
+            return;
+        },
+        // 한 번만 수집하고 종료: jjambot poll
+        Some("poll") => {
+            let client = MndClient::from_env(&state.config.jjam_token);
+            for unit in unit_codes() {
+                match poll_unit(&state, &client, &unit, &mut unit_fetch(&unit)).await {
+                    Ok(changes) => println!("{}: ok ({} upcoming meals changed)", unit, changes.len()),
+                    Err(e) => {
+                        eprintln!("poll_error({}): {}", unit, e);
+                        std::process::exit(1);
+                    },
+                }
+            }
+            return;
+        },
+        // 저장된 식단 검사: jjambot check <부대코드> [YYYY-MM-DD부터, 기본 오늘]
+        Some("check") => {
+            if args.len() < 3 {
+                eprintln!("usage: {} check <unit code> [YYYY-MM-DD]", args[0]);
+                std::process::exit(2);
+            }
+            let from = match args.get(3) {
+                Some(date) => match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+                    Ok(date) => date,
+                    Err(_) => {
+                        eprintln!("invalid date: {}", date);
+                        std::process::exit(2);
+                    },
+                },
+                None => time_now().date_naive(),
+            };
+            match inspect_menus(&state, &args[2], from).await {
+                Ok(findings) => {
+                    for finding in &findings {
+                        println!("{}", finding);
+                    }
+                    println!("{}: {} findings", args[2], findings.len());
+                },
+                Err(e) => {
+                    eprintln!("check_error({}): {}", args[2], e);
+                    std::process::exit(1);
+                },
+            }
+            return;
+        },
+        // 저장된 식단으로 답장 내용 확인: jjambot menu <부대코드> <YYYY-MM-DD> <아침|점심|저녁|간식>
+        Some("menu") => {
+            let meal = match args.get(4).map(String::as_str) {
+                Some("아침") => MealType::Breakfast,
+                Some("점심") => MealType::Lunch,
+                Some("저녁") => MealType::Dinner,
+                Some("간식") => MealType::Snack,
+                _ => {
+                    eprintln!("usage: {} menu <unit code> <YYYY-MM-DD> <아침|점심|저녁|간식>", args[0]);
+                    std::process::exit(2);
+                },
+            };
+            let Ok(date) = NaiveDate::parse_from_str(&args[3], "%Y-%m-%d") else {
+                eprintln!("invalid date: {}", args[3]);
+                std::process::exit(2);
+            };
+            match get_jjam(&state, &args[2], date).await {
+                Ok(jjams) if !jjams.is_empty() => println!("{}", get_menus(&jjams, &args[4], meal, &Profile::default()).await.unwrap_or_default()),
+                Ok(_) => {
+                    eprintln!("{}: no menu for {}", args[2], date);
+                    std::process::exit(1);
+                },
+                Err(e) => {
+                    eprintln!("menu_error: {e}");
+                    std::process::exit(1);
+                },
+            }
+            return;
+        },
+        _ => {},
+    }
+
     tokio::select! {
-        poll_result = jjam_poll() => {
+        poll_result = jjam_poll(state.clone()) => {
             if let Err(e) = poll_result {
                 eprintln!("poll_error: {e}");
             }
         },
-        alarm_result = jjam_alarm() => {
+        alarm_result = jjam_alarm(state.clone()) => {
             if let Err(e) = alarm_result {
                 eprintln!("alarm_error: {e}");
             }
         },
-        daemon_result = tg_reply_daemon() => {
+        backup_result = backup::backup_daemon(state.clone()) => {
+            if let Err(e) = backup_result {
+                eprintln!("backup_error: {e}");
+            }
+        },
+        daemon_result = tg_reply_daemon(state) => {
             if let Err(e) = daemon_result {
                 eprintln!("daemon_error: {e}");
             }
@@ -816,7 +1281,196 @@ fn update_orderidx(floor: usize, change: i32) -> usize {
     let floor = floor & 1;
     (*v)[floor] += change as usize;
     (*v)[floor] %= 3;
-    let ret = (*v)[floor];
+    (*v)[floor]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryStorage;
+    use crate::state::Config;
+
+    fn at(date: &str, time: &str) -> chrono::DateTime<chrono::Utc> {
+        NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M").unwrap().and_utc()
+    }
+
+    fn lunch(date: &str, menu: &str) -> JjamRow {
+        JjamRow { dates: date.to_string(), lunc: menu.to_string(), lunc_cal: Some(300.0), ..Default::default() }
+    }
 
-    return ret;
+    #[test]
+    fn tz_offset_values_and_local_poll_times() {
+        let cases = [("9", 9 * 60), ("+09:00", 9 * 60), ("-03:30", -(3 * 60 + 30)), ("0", 0), (" 14 ", 14 * 60)];
+        for (value, minutes) in cases {
+            assert_eq!(parse_tz_offset(value), Some(Duration::minutes(minutes)), "{}", value);
+        }
+        for value in ["", "KST", "15", "9:60", "+9:x"] {
+            assert_eq!(parse_tz_offset(value), None, "{}", value);
+        }
+
+        // 수집 시각은 현지 시각: UTC 21:30 은 한국(+09:00) 다음날 06:30, 미국 동부(-05:00) 16:30
+        let schedule: Schedule = "0 6 * * *".parse().unwrap();
+        let utc = at("2024-03-10", "21:30");
+        let kst = (utc + parse_tz_offset("+09:00").unwrap()).naive_utc();
+        assert_eq!(schedule.next_after(kst), Some(at("2024-03-12", "06:00").naive_utc()));
+        let est = (utc + parse_tz_offset("-05:00").unwrap()).naive_utc();
+        assert_eq!(schedule.next_after(est), Some(at("2024-03-11", "06:00").naive_utc()));
+    }
+
+    #[tokio::test]
+    async fn alarm_sends_booked_menu_of_users_unit() {
+        let storage = MemoryStorage::new();
+        storage.set_user_unit(1, "1111").await.unwrap();
+        storage.add_book(1, "11:00", "lunch").await.unwrap();
+        storage.add_book(2, "11:30", "lunch").await.unwrap();
+        let jjams = HashMap::from([
+            (("1111".to_string(), 0), vec![lunch("2024-01-02", "짜장밥(5.6)")]),
+            (("1111".to_string(), 1), vec![lunch("2024-01-03", "비빔밥")]),
+        ]);
+
+        let messages = alarm_messages(&storage, at("2024-01-02", "11:00"), &jjams).await.unwrap();
+        assert_eq!(messages.len(), 1);
+        let (userid, msg) = &messages[0];
+        assert_eq!(*userid, 1);
+        assert!(msg.starts_with("<b>점심 메뉴</b>"), "{}", msg);
+        assert!(msg.contains("짜장밥 (5,6) [300kcal]"), "{}", msg);
+        assert!(!msg.contains("비빔밥"), "{}", msg);
+    }
+
+    #[tokio::test]
+    async fn alarm_marks_allergens_and_skips_saturday_lunch() {
+        let storage = MemoryStorage::new();
+        storage.set_user_unit(1, "1111").await.unwrap();
+        storage.add_allergy(1, 5).await.unwrap();
+        storage.add_book(1, "11:00", "lunch").await.unwrap();
+        storage.add_book(1, "20:00", "nextbreakfast").await.unwrap();
+        let breakfast = JjamRow { dates: "2024-01-03".to_string(), brst: "우유(2)".to_string(), ..Default::default() };
+        let jjams = HashMap::from([
+            (("1111".to_string(), 0), vec![lunch("2024-01-02", "짜장밥(5.6)")]),
+            (("1111".to_string(), 1), vec![breakfast]),
+        ]);
+
+        let messages = alarm_messages(&storage, at("2024-01-02", "11:00"), &jjams).await.unwrap();
+        assert!(messages[0].1.contains("<b>짜장밥"), "{}", messages[0].1);
+        assert!(messages[0].1.contains("알레르기 주의: 대두"), "{}", messages[0].1);
+
+        // 2024-01-06 은 토요일
+        assert!(alarm_messages(&storage, at("2024-01-06", "11:00"), &jjams).await.unwrap().is_empty());
+
+        let messages = alarm_messages(&storage, at("2024-01-02", "20:00"), &jjams).await.unwrap();
+        assert!(messages[0].1.starts_with("<b>내일 아침 메뉴</b>"), "{}", messages[0].1);
+        assert!(messages[0].1.contains("우유"), "{}", messages[0].1);
+    }
+
+    #[tokio::test]
+    async fn alarm_skips_kinds_that_no_longer_exist() {
+        let storage = MemoryStorage::new();
+        storage.add_book(1, "09:00", "brunch").await.unwrap();
+        assert!(alarm_messages(&storage, at("2024-01-02", "09:00"), &HashMap::new()).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn store_jjam_replaces_changed_dates_in_storage() {
+        let tomorrow = time_now().date_naive() + Duration::days(1);
+        let date = tomorrow.format("%Y-%m-%d").to_string();
+        let storage = MemoryStorage::new();
+        storage.put_menus("1111", vec![lunch(&date, "짜장밥(5.6)"), lunch("2020-01-01", "없어진 식단")]);
+        let state = AppState::with_storage(Box::new(storage), Config::default()).await.unwrap();
+        let raw = RawJjamRow { dates: date.clone(), lunc: "비빔밥".to_string(), lunc_cal: "300kcal".to_string(), ..Default::default() };
+
+        let changes = store_jjam(&state, "1111", vec![raw.clone()], true).await.unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].meal, MealType::Lunch);
+        assert_eq!(changes[0].before, ["짜장밥 (5,6) [300kcal]"]);
+        assert_eq!(changes[0].after, ["비빔밥 [300kcal]"]);
+        assert_eq!(state.storage.menus("1111", tomorrow).await.unwrap()[0].lunc, "비빔밥");
+        // 새로 받은 식단에 없는 날짜는 지움
+        let range = state.storage.menu_range("1111").await.unwrap().unwrap();
+        assert_eq!((range.first, range.last, range.dates, range.rows), (tomorrow, tomorrow, 1, 1));
+
+        // 같은 식단을 다시 받으면 바뀐 것 없음
+        assert!(store_jjam(&state, "1111", vec![raw], true).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn store_jjam_merges_partial_import() {
+        let today = time_now().date_naive();
+        let day = |days: i64| (today + Duration::days(days)).format("%Y-%m-%d").to_string();
+        let storage = MemoryStorage::new();
+        storage.put_menus("1111", vec![lunch(&day(1), "짜장밥"), lunch(&day(2), "카레"), lunch("2020-01-01", "지난 식단")]);
+        let state = AppState::with_storage(Box::new(storage), Config::default()).await.unwrap();
+        let raw = RawJjamRow { dates: day(1), lunc: "비빔밥".to_string(), lunc_cal: "300kcal".to_string(), ..Default::default() };
+
+        // 파일에 없는 날짜는 그대로 두고 알림도 안 보냄
+        let changes = store_jjam(&state, "1111", vec![raw], false).await.unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].date, today + Duration::days(1));
+        assert_eq!(state.storage.menus("1111", today + Duration::days(1)).await.unwrap()[0].lunc, "비빔밥");
+        assert_eq!(state.storage.menus("1111", today + Duration::days(2)).await.unwrap()[0].lunc, "카레");
+        let first = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
+        assert_eq!(state.storage.menus("1111", first).await.unwrap()[0].lunc, "지난 식단");
+    }
+
+    #[tokio::test]
+    async fn store_jjam_drops_dates_past_keep_days() {
+        let today = time_now().date_naive();
+        let day = |days: i64| (today + Duration::days(days)).format("%Y-%m-%d").to_string();
+        let storage = MemoryStorage::new();
+        storage.put_menus("1111", vec![lunch(&day(-10), "지난 식단"), lunch(&day(-1), "어제 식단")]);
+        let config = Config { keep_days: Some(3), ..Default::default() };
+        let state = AppState::with_storage(Box::new(storage), config).await.unwrap();
+        let raw = |date: String| RawJjamRow { dates: date, lunc: "비빔밥".to_string(), ..Default::default() };
+
+        // 가져오기여도 보관 기간이 지난 날짜는 지우고 새로 넣지도 않음
+        store_jjam(&state, "1111", vec![raw(day(-20)), raw(day(1))], false).await.unwrap();
+        let range = state.storage.menu_range("1111").await.unwrap().unwrap();
+        assert_eq!((range.first, range.last, range.dates, range.rows), (today - Duration::days(1), today + Duration::days(1), 2, 2));
+        assert!(list_menus(&state).await.unwrap().contains("3일보다 오래된 식단은 지움"));
+    }
+
+    // ORDERIDX 는 전역이라 식사순서는 이 테스트 하나에서만 바꿈
+    #[tokio::test]
+    async fn order_is_stored_and_sent() {
+        let storage = MemoryStorage::new();
+        let now = at("2024-01-02", "08:00");
+        *ORDERIDX.lock().unwrap() = [0, 0];
+
+        // 기록이 없으면 첫 순서로 시작하고 기록해 둠
+        assert_eq!(get_last_order(1, now.naive_utc(), &storage).await.unwrap(), 0);
+        assert_eq!(storage.last_order(1).await.unwrap(), Some(0));
+
+        insert_order(1, now.naive_utc(), &storage).await.unwrap();
+        insert_order(1, (now + Duration::hours(10)).naive_utc(), &storage).await.unwrap();
+        assert_eq!(storage.last_order(1).await.unwrap(), Some(2));
+        assert_eq!(get_last_order(1, now.naive_utc(), &storage).await.unwrap(), 2);
+        assert_eq!(storage.last_order(0).await.unwrap(), None);
+
+        storage.add_book(7, "17:00", "dinnerorder").await.unwrap();
+        let messages = alarm_messages(&storage, at("2024-01-02", "17:00"), &HashMap::new()).await.unwrap();
+        assert_eq!(messages, vec![(7, format!("식사순서: {}", ORDERS[2]))]);
+
+        // 세 번이면 처음 순서로 돌아옴
+        insert_order(1, (now + Duration::days(1)).naive_utc(), &storage).await.unwrap();
+        assert_eq!(storage.last_order(1).await.unwrap(), Some(0));
+    }
+
+    #[tokio::test]
+    async fn dish_history_matches_wildcards_literally() {
+        let state = AppState::with_storage(Box::new(MemoryStorage::new()), Config::default()).await.unwrap();
+        let today = time_now().date_naive();
+        let day = |days: i64| (today - Duration::days(days)).format("%Y-%m-%d").to_string();
+        // 글자 그대로 맞는 요리가 더 오래 전, 와일드카드로만 맞는 요리가 더 최근
+        let dates = BTreeMap::from([
+            (day(3), vec![lunch(&day(3), "우유 100%"), lunch(&day(3), "과일_샐러드")]),
+            (day(2), vec![lunch(&day(2), "a\\b")]),
+            (day(1), vec![lunch(&day(1), "우유 1000"), lunch(&day(1), "과일생샐러드")]),
+        ]);
+        archive_jjam(&state.db, "1111", &dates).await.unwrap();
+
+        for (keyword, days, expected) in [("100%", 3, "우유 100%"), ("일_샐", 3, "과일_샐러드"), ("\\", 2, "a\\b"), ("샐러드", 1, "과일생샐러드")] {
+            let reply = dish_history(&state, "1111", keyword).await.unwrap();
+            assert!(reply.contains(&format!("마지막: {} 점심 ({})", day(days), expected)), "{}: {}", keyword, reply);
+        }
+        assert!(dish_history(&state, "1111", "우유%").await.unwrap().ends_with("기록이 없습니다."));
+    }
 }
@@ -0,0 +1,206 @@
+// 테스트용 저장소
+//
+// 명령, 알람 동작을 data/ 나 DB 없이 확인할 때 AppState::with_storage 로 넣어서 씀
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::storage::{menu_range, DishRow, MenuRange, Storage, TimerData, UserData};
+use crate::{group_by_date, time_now, JjamRow, MealType, Profile, ShowError};
+
+#[derive(Clone, Debug, Default)]
+struct MemoryUser {
+    unit: Option<String>,
+    admin: bool,
+    allergies: BTreeSet<u8>,
+    // tag -> until
+    avoids: BTreeMap<String, Option<String>>,
+}
+
+// 프로세스 안에서만 들고 있는 저장소. 식단은 put_menus 나 save_menus 로 넣어 둠
+#[derive(Default)]
+pub struct MemoryStorage {
+    users: Mutex<BTreeMap<i64, MemoryUser>>,
+    // (userid, what) -> timewhen
+    timer: Mutex<BTreeMap<(i64, String), String>>,
+    // 층별 (datestime, orderidx), 넣은 순서대로
+    orders: Mutex<[Vec<(NaiveDateTime, usize)>; 2]>,
+    menus: Mutex<HashMap<String, Vec<JjamRow>>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 부대 식단을 통째로 바꿈
+    pub fn put_menus(&self, unit: &str, rows: Vec<JjamRow>) {
+        self.menus.lock().unwrap().insert(unit.to_string(), rows);
+    }
+}
+
+#[async_trait]
+impl Storage for MemoryStorage {
+    async fn add_user(&self, user: &UserData, admin: bool) -> Result<bool, ShowError> {
+        let mut users = self.users.lock().unwrap();
+        if users.contains_key(&user.userid) {
+            return Ok(false);
+        }
+        users.insert(user.userid, MemoryUser { admin, ..Default::default() });
+        Ok(true)
+    }
+
+    async fn user_unit(&self, userid: i64) -> Result<Option<String>, ShowError> {
+        Ok(self.users.lock().unwrap().get(&userid).and_then(|u| u.unit.clone()))
+    }
+
+    async fn set_user_unit(&self, userid: i64, unit: &str) -> Result<(), ShowError> {
+        self.users.lock().unwrap().entry(userid).or_default().unit = Some(unit.to_string());
+        Ok(())
+    }
+
+    async fn is_admin(&self, userid: i64) -> Result<bool, ShowError> {
+        Ok(self.users.lock().unwrap().get(&userid).is_some_and(|u| u.admin))
+    }
+
+    async fn admins(&self) -> Result<Vec<i64>, ShowError> {
+        Ok(self.users.lock().unwrap().iter().filter(|(_, u)| u.admin).map(|(id, _)| *id).collect())
+    }
+
+    async fn profile(&self, userid: i64) -> Result<Profile, ShowError> {
+        let today = time_now().date_naive().format("%Y-%m-%d").to_string();
+        Ok(self.users.lock().unwrap().get(&userid).map(|u| Profile {
+            allergies: u.allergies.iter().copied().collect(),
+            avoids: u.avoids.iter()
+                .filter(|(_, until)| until.as_ref().is_none_or(|until| *until >= today))
+                .map(|(tag, _)| tag.clone())
+                .collect(),
+        }).unwrap_or_default())
+    }
+
+    async fn add_allergy(&self, userid: i64, allergen: u8) -> Result<(), ShowError> {
+        self.users.lock().unwrap().entry(userid).or_default().allergies.insert(allergen);
+        Ok(())
+    }
+
+    async fn del_allergy(&self, userid: i64, allergen: Option<u8>) -> Result<(), ShowError> {
+        if let Some(user) = self.users.lock().unwrap().get_mut(&userid) {
+            user.allergies.retain(|a| allergen.is_some_and(|allergen| allergen != *a));
+        }
+        Ok(())
+    }
+
+    async fn add_avoid(&self, userid: i64, tag: &str, until: Option<&str>) -> Result<(), ShowError> {
+        self.users.lock().unwrap().entry(userid).or_default().avoids.insert(tag.to_string(), until.map(str::to_string));
+        Ok(())
+    }
+
+    async fn del_avoid(&self, userid: i64, tag: Option<&str>) -> Result<(), ShowError> {
+        if let Some(user) = self.users.lock().unwrap().get_mut(&userid) {
+            user.avoids.retain(|t, _| tag.is_some_and(|tag| tag != t));
+        }
+        Ok(())
+    }
+
+    async fn add_book(&self, userid: i64, timewhen: &str, what: &str) -> Result<(), ShowError> {
+        self.timer.lock().unwrap().insert((userid, what.to_string()), timewhen.to_string());
+        Ok(())
+    }
+
+    async fn del_book(&self, userid: i64, what: &str) -> Result<(), ShowError> {
+        self.timer.lock().unwrap().remove(&(userid, what.to_string()));
+        Ok(())
+    }
+
+    async fn books_of(&self, userid: i64) -> Result<Vec<(String, String)>, ShowError> {
+        let mut books: Vec<(String, String)> = self.timer.lock().unwrap().iter()
+            .filter(|((id, _), _)| *id == userid)
+            .map(|((_, what), when)| (what.clone(), when.clone()))
+            .collect();
+        books.sort_by(|a, b| a.1.cmp(&b.1));
+        Ok(books)
+    }
+
+    async fn books_at(&self, timewhen: &str) -> Result<Vec<TimerData>, ShowError> {
+        let users = self.users.lock().unwrap();
+        Ok(self.timer.lock().unwrap().iter()
+            .filter(|(_, when)| *when == timewhen)
+            .map(|((userid, what), _)| TimerData {
+                userid: *userid,
+                what: what.clone(),
+                unit: users.get(userid).and_then(|u| u.unit.clone()),
+            })
+            .collect())
+    }
+
+    async fn subscribers(&self, unit: &str, default_unit: &str, kinds: &[&str]) -> Result<Vec<i64>, ShowError> {
+        let users = self.users.lock().unwrap();
+        let mut userids: Vec<i64> = self.timer.lock().unwrap().keys()
+            .filter(|(userid, what)| {
+                let user_unit = users.get(userid).and_then(|u| u.unit.as_deref()).unwrap_or(default_unit);
+                user_unit == unit && kinds.contains(&what.as_str())
+            })
+            .map(|(userid, _)| *userid)
+            .collect();
+        userids.dedup();
+        Ok(userids)
+    }
+
+    async fn last_order(&self, floor: usize) -> Result<Option<usize>, ShowError> {
+        Ok(self.orders.lock().unwrap()[floor & 1].iter()
+            .max_by_key(|(datestime, _)| *datestime)
+            .map(|(_, idx)| *idx))
+    }
+
+    async fn insert_order(&self, datestime: NaiveDateTime, floor: usize, orderidx: usize) -> Result<(), ShowError> {
+        self.orders.lock().unwrap()[floor & 1].push((datestime, orderidx));
+        Ok(())
+    }
+
+    async fn menus(&self, unit: &str, date: NaiveDate) -> Result<Vec<JjamRow>, ShowError> {
+        let date = date.format("%Y-%m-%d").to_string();
+        Ok(self.menus.lock().unwrap().get(unit)
+            .map(|rows| rows.iter().filter(|r| r.dates == date).cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn menus_from(&self, unit: &str, from: NaiveDate) -> Result<Vec<JjamRow>, ShowError> {
+        let from = from.format("%Y-%m-%d").to_string();
+        Ok(self.menus.lock().unwrap().get(unit)
+            .map(|rows| rows.iter().filter(|r| r.dates >= from).cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn menu_range(&self, unit: &str) -> Result<Option<MenuRange>, ShowError> {
+        let menus = self.menus.lock().unwrap();
+        let dates: BTreeSet<String> = menus.get(unit).map(|rows| rows.iter().map(|r| r.dates.clone()).collect()).unwrap_or_default();
+        let rows = menus.get(unit).map_or(0, Vec::len) as i64;
+        Ok(menu_range(dates.first().cloned(), dates.last().cloned(), dates.len() as i64, rows))
+    }
+
+    async fn dishes_from(&self, unit: &str, from: NaiveDate) -> Result<Vec<DishRow>, ShowError> {
+        let rows = self.menus_from(unit, from).await?;
+        let mut dishes = Vec::new();
+        for (date, rows) in group_by_date(rows) {
+            for meal in [MealType::Breakfast, MealType::Lunch, MealType::Dinner, MealType::Snack] {
+                dishes.extend(meal.dishes(&rows).into_iter().map(|dish| DishRow {
+                    dates: date.clone(),
+                    meal: meal.column().to_string(),
+                    name: dish.name,
+                    kcal: dish.kcal,
+                }));
+            }
+        }
+        Ok(dishes)
+    }
+
+    async fn save_menus(&self, unit: &str, dates: &BTreeMap<String, Vec<JjamRow>>) -> Result<(), ShowError> {
+        let mut menus = self.menus.lock().unwrap();
+        let stored = menus.entry(unit.to_string()).or_default();
+        stored.retain(|r| !dates.contains_key(&r.dates));
+        stored.extend(dates.values().flatten().cloned());
+        Ok(())
+    }
+}
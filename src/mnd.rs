@@ -0,0 +1,317 @@
+// 국방부 OpenAPI (openapi.mnd.go.kr) 클라이언트
+//
+// 응답 형태
+// 정상: {"<서비스명>": {"list_total_count": n, "row": [...]}}
+// 오류: {"RESULT": {"CODE": "INFO-200", "MESSAGE": "해당하는 데이터가 없습니다."}}
+// 서비스 객체 안쪽에 RESULT가 같이 오는 경우도 있어서 둘 다 확인함
+use serde::{de::DeserializeOwned, Deserialize};
+
+pub const DEFAULT_BASE_URL: &str = "https://openapi.mnd.go.kr";
+pub const MND_BASE_URL: &str = "MND_BASE_URL";
+
+// 한 번에 요청할 수 있는 최대 건수 (ERROR-336)
+pub const MAX_PAGE_SIZE: u32 = 1000;
+
+const USER_AGENT_FIREFOX: &str = "Mozilla/5.0 (X11; Linux x86_64;rv:60.0) Gecko/20100101 Firefox/81.0";
+
+#[derive(Debug)]
+pub enum MndError {
+    Http(reqwest::Error),
+    Json(serde_json::Error),
+    // INFO-100: 인증키가 유효하지 않음
+    InvalidKey(String),
+    // INFO-200: 해당하는 데이터가 없음
+    NoData,
+    // ERROR-337: 일별 트래픽 제한 초과
+    QuotaExceeded(String),
+    // 그 밖의 RESULT 코드
+    Api { code: String, message: String },
+    // 응답에 서비스 객체도 RESULT도 없음
+    MissingDataset(String),
+}
+
+impl std::fmt::Display for MndError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            MndError::Http(e) => write!(f, "HTTP Error: {}", e),
+            MndError::Json(e) => write!(f, "JSON Error: {}", e),
+            MndError::InvalidKey(msg) => write!(f, "Invalid API key: {}", msg),
+            MndError::NoData => write!(f, "No data"),
+            MndError::QuotaExceeded(msg) => write!(f, "Quota exceeded: {}", msg),
+            MndError::Api { code, message } => write!(f, "API Error {}: {}", code, message),
+            MndError::MissingDataset(service) => write!(f, "{} not found in response", service),
+        }
+    }
+}
+
+impl MndError {
+    // 다시 요청하면 나아질 수 있는 오류인지 (인증키, 서비스명 오류 등은 재시도해도 소용없음)
+    pub fn is_transient(&self) -> bool {
+        match self {
+            MndError::Http(_) | MndError::Json(_) => true,
+            MndError::Api { code, .. } => matches!(code.as_str(), "ERROR-500" | "ERROR-600" | "ERROR-601"),
+            _ => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for MndError {
+    fn from(value: reqwest::Error) -> Self {
+        Self::Http(value)
+    }
+}
+
+impl From<serde_json::Error> for MndError {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Json(value)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct MndResult {
+    #[serde(rename = "CODE")]
+    code: String,
+    #[serde(rename = "MESSAGE", default)]
+    message: String,
+}
+
+impl MndResult {
+    fn into_error(self) -> Option<MndError> {
+        match self.code.as_str() {
+            "INFO-000" => None,
+            "INFO-100" => Some(MndError::InvalidKey(self.message)),
+            "INFO-200" => Some(MndError::NoData),
+            "ERROR-337" => Some(MndError::QuotaExceeded(self.message)),
+            _ => Some(MndError::Api { code: self.code, message: self.message }),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MndPage<T> {
+    pub list_total_count: u32,
+    #[serde(default = "Vec::new")]
+    pub row: Vec<T>,
+}
+
+pub struct MndClient {
+    http: reqwest::Client,
+    base_url: String,
+    token: String,
+}
+
+impl MndClient {
+    pub fn new(base_url: &str, token: &str) -> Self {
+        Self {
+            http: reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(30))
+                .build()
+                .unwrap_or_default(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+        }
+    }
+
+    // MND_BASE_URL 로 로컬 대체 서버를 가리킬 수 있음
+    pub fn from_env(token: &str) -> Self {
+        let base_url = std::env::var(MND_BASE_URL).unwrap_or_else(|_| DEFAULT_BASE_URL.to_string());
+        Self::new(&base_url, token)
+    }
+
+    // start, end 는 1부터 시작하는 요청 위치 (양끝 포함)
+    pub async fn fetch<T: DeserializeOwned>(&self, service: &str, start: u32, end: u32) -> Result<MndPage<T>, MndError> {
+        let body = self.http
+            .get(format!("{}/{}/json/{}/{}/{}", self.base_url, self.token, service, start, end))
+            .header(reqwest::header::USER_AGENT, USER_AGENT_FIREFOX)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        parse_page(&body, service)
+    }
+}
+
+// 데이터셋 전체를 page_size 단위로 나눠서 받음
+// 중간에 요청이 실패해도 받아둔 행은 남아 있어서, resume을 다시 부르면 실패한 페이지부터 이어서 받음
+pub struct PagedFetch<T> {
+    service: String,
+    page_size: u32,
+    total: Option<u32>,
+    rows: Vec<T>,
+}
+
+impl<T: DeserializeOwned> PagedFetch<T> {
+    pub fn new(service: &str, page_size: u32) -> Self {
+        Self {
+            service: service.to_string(),
+            page_size: page_size.clamp(1, MAX_PAGE_SIZE),
+            total: None,
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn fetched(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub async fn resume(&mut self, client: &MndClient) -> Result<(), MndError> {
+        while let Some((start, end)) = self.next_range() {
+            let page = client.fetch::<T>(&self.service, start, end).await?;
+            if !self.push(page) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // 다음에 요청할 범위, 다 받았으면 None
+    fn next_range(&self) -> Option<(u32, u32)> {
+        let start = self.rows.len() as u32 + 1;
+        let end = start + self.page_size - 1;
+        match self.total {
+            Some(total) if start > total => None,
+            Some(total) => Some((start, end.min(total))),
+            None => Some((start, end)),
+        }
+    }
+
+    // 받은 페이지를 붙임, 빈 페이지면 더 받을 것이 없으므로 false
+    fn push(&mut self, page: MndPage<T>) -> bool {
+        // 받는 도중에 데이터셋 크기가 바뀌면 앞 페이지와 어긋날 수 있으므로 처음부터 다시 받음
+        if self.total.is_some_and(|total| total != page.list_total_count) {
+            self.total = None;
+            self.rows.clear();
+            return true;
+        }
+        self.total = Some(page.list_total_count);
+        if page.row.is_empty() {
+            return false;
+        }
+        self.rows.extend(page.row);
+        true
+    }
+
+    pub fn rows(&self) -> &[T] {
+        &self.rows
+    }
+}
+
+pub fn parse_page<T: DeserializeOwned>(body: &str, service: &str) -> Result<MndPage<T>, MndError> {
+    let mut shell: serde_json::Map<String, serde_json::Value> = serde_json::from_str(body)?;
+
+    // 서비스명을 그대로 쓰되, 없으면 DS_ 로 시작하는 유일한 키를 데이터셋으로 봄
+    let key = if shell.contains_key(service) {
+        Some(service.to_string())
+    } else {
+        let mut keys = shell.keys().filter(|k| k.starts_with("DS_"));
+        match (keys.next(), keys.next()) {
+            (Some(k), None) => Some(k.clone()),
+            _ => None,
+        }
+    };
+
+    let Some(mut dataset) = key.and_then(|k| shell.remove(&k)) else {
+        return Err(match shell.remove("RESULT") {
+            Some(result) => serde_json::from_value::<MndResult>(result)?
+                .into_error()
+                .unwrap_or_else(|| MndError::MissingDataset(service.to_string())),
+            None => MndError::MissingDataset(service.to_string()),
+        });
+    };
+
+    if let Some(result) = dataset.get_mut("RESULT").map(serde_json::Value::take) {
+        if let Some(e) = serde_json::from_value::<MndResult>(result)?.into_error() {
+            return Err(e);
+        }
+    }
+    Ok(serde_json::from_value(dataset)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Row {
+        n: u32,
+    }
+
+    fn page(total: u32, rows: &[u32]) -> MndPage<Row> {
+        let rows: Vec<String> = rows.iter().map(|n| format!(r#"{{"n": {n}}}"#)).collect();
+        let body = format!(r#"{{"DS_TB_MNDT_DATEBYMLSVC_9999": {{"list_total_count": {total}, "row": [{}]}}}}"#, rows.join(","));
+        parse_page(&body, "DS_TB_MNDT_DATEBYMLSVC_9999").unwrap()
+    }
+
+    #[test]
+    fn parse_page_finds_dataset() {
+        let body = r#"{"DS_TB_MNDT_DATEBYMLSVC_1234": {"list_total_count": 2, "RESULT": {"CODE": "INFO-000", "MESSAGE": "정상 처리되었습니다."}, "row": [{"n": 1}, {"n": 2}]}}"#;
+        // 서비스명이 그대로 있으면 그 키를 씀
+        let page = parse_page::<Row>(body, "DS_TB_MNDT_DATEBYMLSVC_1234").unwrap();
+        assert_eq!(page.list_total_count, 2);
+        assert_eq!(page.row, vec![Row { n: 1 }, Row { n: 2 }]);
+        // 서비스명이 달라도 DS_ 키가 하나뿐이면 그것을 씀
+        let page = parse_page::<Row>(body, "DS_TB_MNDT_DATEBYMLSVC_ATC").unwrap();
+        assert_eq!(page.row.len(), 2);
+        // row 가 없으면 빈 목록
+        let page = parse_page::<Row>(r#"{"DS_A": {"list_total_count": 0}}"#, "DS_A").unwrap();
+        assert!(page.row.is_empty());
+    }
+
+    #[test]
+    fn parse_page_maps_result_codes() {
+        let result = |code: &str| format!(r#"{{"RESULT": {{"CODE": "{code}", "MESSAGE": "msg"}}}}"#);
+        let parse = |body: &str| parse_page::<Row>(body, "DS_A").unwrap_err();
+
+        assert!(matches!(parse(&result("INFO-100")), MndError::InvalidKey(m) if m == "msg"));
+        assert!(matches!(parse(&result("INFO-200")), MndError::NoData));
+        assert!(matches!(parse(&result("ERROR-337")), MndError::QuotaExceeded(_)));
+        let e = parse(&result("ERROR-500"));
+        assert!(matches!(&e, MndError::Api { code, .. } if code == "ERROR-500"));
+        assert!(e.is_transient());
+        assert!(!parse(&result("ERROR-300")).is_transient());
+        // 정상 코드인데 데이터셋이 없으면
+        assert!(matches!(parse(&result("INFO-000")), MndError::MissingDataset(s) if s == "DS_A"));
+        // 서비스 객체 안쪽의 RESULT
+        assert!(matches!(parse(r#"{"DS_A": {"RESULT": {"CODE": "INFO-200", "MESSAGE": ""}}}"#), MndError::NoData));
+        // DS_ 키가 여럿이면 어느 것인지 모름
+        assert!(matches!(parse(r#"{"DS_B": {"list_total_count": 0}, "DS_C": {"list_total_count": 0}}"#), MndError::MissingDataset(_)));
+        assert!(matches!(parse("not json"), MndError::Json(_)));
+    }
+
+    #[test]
+    fn paged_fetch_requests_until_total() {
+        let mut fetch = PagedFetch::<Row>::new("DS_TB_MNDT_DATEBYMLSVC_9999", 3);
+        assert_eq!(fetch.next_range(), Some((1, 3)));
+        assert!(fetch.push(page(7, &[1, 2, 3])));
+        assert_eq!(fetch.next_range(), Some((4, 6)));
+        assert!(fetch.push(page(7, &[4, 5, 6])));
+        // 마지막 페이지는 page_size 보다 짧음
+        assert_eq!(fetch.next_range(), Some((7, 7)));
+        assert!(fetch.push(page(7, &[7])));
+        assert_eq!(fetch.next_range(), None);
+        assert_eq!(fetch.fetched(), 7);
+        assert_eq!(fetch.rows().iter().map(|r| r.n).collect::<Vec<_>>(), (1..=7).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn paged_fetch_stops_on_empty_page_and_restarts_on_total_change() {
+        let mut fetch = PagedFetch::<Row>::new("DS_TB_MNDT_DATEBYMLSVC_9999", 2);
+        assert!(!fetch.push(page(0, &[])));
+        assert_eq!(fetch.next_range(), None);
+
+        let mut fetch = PagedFetch::<Row>::new("DS_TB_MNDT_DATEBYMLSVC_9999", 2);
+        assert!(fetch.push(page(4, &[1, 2])));
+        // 도중에 건수가 바뀌면 받아둔 것을 버리고 처음부터
+        assert!(fetch.push(page(5, &[3, 4])));
+        assert_eq!(fetch.fetched(), 0);
+        assert_eq!(fetch.next_range(), Some((1, 2)));
+    }
+
+    #[test]
+    fn page_size_is_clamped() {
+        assert_eq!(PagedFetch::<Row>::new("DS_A", 0).next_range(), Some((1, 1)));
+        assert_eq!(PagedFetch::<Row>::new("DS_A", 5000).next_range(), Some((1, MAX_PAGE_SIZE)));
+    }
+}
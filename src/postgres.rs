@@ -0,0 +1,367 @@
+// DATABASE_URL=postgres://... 일 때 쓰는 저장소
+//
+// 스키마는 migrations_pg/ 로 관리하고, 처음 연결할 때 data/jjambot.sqlite 의 사용자, 예약, 식사순서와
+// 부대별 식단을 한 번만 옮겨 옴 (SQLite 쪽은 지우지 않음)
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use chrono::{NaiveDate, NaiveDateTime};
+use sqlx::migrate::Migrator;
+use sqlx::postgres::{PgConnectOptions, PgPool, PgRow};
+use sqlx::{Pool, Postgres, Row, Sqlite};
+
+use crate::storage::{menu_range, replace_menus, DishRow, MenuRange, Storage, TimerData, UserData};
+use crate::{group_by_date, time_now, unit_codes, JjamRow, Profile, ShowError, ORDERS};
+
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations_pg");
+
+const SQLITE_IMPORTED: &str = "sqlite_import";
+
+impl<'r> sqlx::FromRow<'r, PgRow> for JjamRow {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            dates: row.try_get("dates")?,
+            brst: row.try_get("brst")?,
+            brst_cal: row.try_get("brst_cal")?,
+            lunc: row.try_get("lunc")?,
+            lunc_cal: row.try_get("lunc_cal")?,
+            dinr: row.try_get("dinr")?,
+            dinr_cal: row.try_get("dinr_cal")?,
+            adspcfd: row.try_get("adspcfd")?,
+            adspcfd_cal: row.try_get("adspcfd_cal")?,
+            sum_cal: row.try_get("sum_cal")?,
+        })
+    }
+}
+
+pub struct PostgresStorage {
+    db: Pool<Postgres>,
+}
+
+impl PostgresStorage {
+    pub async fn connect(url: &str, sqlite: &Pool<Sqlite>) -> Result<Self, ShowError> {
+        Self::connect_with(PgConnectOptions::from_str(url)?, sqlite).await
+    }
+
+    async fn connect_with(options: PgConnectOptions, sqlite: &Pool<Sqlite>) -> Result<Self, ShowError> {
+        let db = PgPool::connect_with(options).await?;
+        MIGRATOR.run(&db).await.map_err(|e| format!("migration failed: {}", e))?;
+        let storage = Self { db };
+
+        let imported = sqlx::query("SELECT 1 FROM meta WHERE key=$1")
+            .bind(SQLITE_IMPORTED)
+            .fetch_optional(&storage.db).await?
+            .is_some();
+        if !imported {
+            storage.import_sqlite(sqlite).await?;
+        }
+        // dishes 테이블이 생기기 전에 옮긴 식단이면 한 번 나눠 넣음
+        let backfill = sqlx::query("SELECT 1 FROM menus WHERE NOT EXISTS (SELECT 1 FROM dishes) LIMIT 1")
+            .fetch_optional(&storage.db).await?
+            .is_some();
+        if backfill {
+            storage.backfill_dishes().await?;
+        }
+        Ok(storage)
+    }
+
+    // SQLite 에서 쓰던 것을 옮김. 외래키 순서대로 users 먼저
+    async fn import_sqlite(&self, sqlite: &Pool<Sqlite>) -> Result<(), ShowError> {
+        let mut tx = self.db.begin().await?;
+        let users = sqlx::query("SELECT userid, CAST(username AS TEXT), CAST(realname AS TEXT), admin, unit FROM users ORDER BY id")
+            .fetch_all(sqlite).await?;
+        for r in &users {
+            sqlx::query("INSERT INTO users (userid, username, realname, admin, unit) VALUES ($1, $2, $3, $4, $5) ON CONFLICT DO NOTHING")
+                .bind(r.get::<i64, _>(0))
+                .bind(r.get::<Option<String>, _>(1))
+                .bind(r.get::<Option<String>, _>(2))
+                .bind(r.get::<i32, _>(3))
+                .bind(r.get::<Option<String>, _>(4))
+                .execute(&mut *tx).await?;
+        }
+        let timer = sqlx::query("SELECT userid, timewhen, what FROM timer ORDER BY id").fetch_all(sqlite).await?;
+        for r in &timer {
+            sqlx::query("INSERT INTO timer (userid, timewhen, what) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING")
+                .bind(r.get::<i64, _>(0))
+                .bind(r.get::<String, _>(1))
+                .bind(r.get::<Option<String>, _>(2))
+                .execute(&mut *tx).await?;
+        }
+        let allergies = sqlx::query("SELECT userid, allergen FROM allergies ORDER BY id").fetch_all(sqlite).await?;
+        for r in &allergies {
+            sqlx::query("INSERT INTO allergies (userid, allergen) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+                .bind(r.get::<i64, _>(0))
+                .bind(r.get::<i32, _>(1))
+                .execute(&mut *tx).await?;
+        }
+        let prefs = sqlx::query("SELECT userid, tag, until FROM prefs ORDER BY id").fetch_all(sqlite).await?;
+        for r in &prefs {
+            sqlx::query("INSERT INTO prefs (userid, tag, until) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING")
+                .bind(r.get::<i64, _>(0))
+                .bind(r.get::<String, _>(1))
+                .bind(r.get::<Option<String>, _>(2))
+                .execute(&mut *tx).await?;
+        }
+        let orders = sqlx::query("SELECT datestime, floor, ordertxt, orderidx FROM orders ORDER BY id").fetch_all(sqlite).await?;
+        for r in &orders {
+            sqlx::query("INSERT INTO orders (datestime, floor, ordertxt, orderidx) VALUES ($1, $2, $3, $4)")
+                .bind(r.get::<Option<String>, _>(0))
+                .bind(r.get::<Option<i32>, _>(1))
+                .bind(r.get::<Option<String>, _>(2))
+                .bind(r.get::<Option<i32>, _>(3))
+                .execute(&mut *tx).await?;
+        }
+        println!("sqlite_import: {} users, {} timers, {} allergies, {} prefs, {} orders",
+            users.len(), timer.len(), allergies.len(), prefs.len(), orders.len());
+
+        for unit in unit_codes() {
+            let rows = sqlx::query_as::<_, JjamRow>("SELECT * FROM menus WHERE unit=? ORDER BY id")
+                .bind(&unit)
+                .fetch_all(sqlite).await?;
+            println!("sqlite_import: {} menu rows for {}", rows.len(), unit);
+            for (date, rows) in group_by_date(rows) {
+                replace_menus(&mut tx, &unit, &date, &rows).await?;
+            }
+        }
+
+        sqlx::query("INSERT INTO meta (key, value) VALUES ($1, $2)")
+            .bind(SQLITE_IMPORTED)
+            .bind(time_now().format("%Y-%m-%d %H:%M:%S").to_string())
+            .execute(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn backfill_dishes(&self) -> Result<(), ShowError> {
+        let mut tx = self.db.begin().await?;
+        let rows = sqlx::query("SELECT unit, dates FROM menus GROUP BY unit, dates").fetch_all(&mut *tx).await?;
+        for r in &rows {
+            let (unit, date) = (r.get::<String, _>(0), r.get::<String, _>(1));
+            let menus = sqlx::query_as::<_, JjamRow>("SELECT * FROM menus WHERE unit=$1 AND dates=$2 ORDER BY id")
+                .bind(&unit)
+                .bind(&date)
+                .fetch_all(&mut *tx).await?;
+            replace_menus(&mut tx, &unit, &date, &menus).await?;
+        }
+        tx.commit().await?;
+        println!("dishes backfilled for {} dates", rows.len());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Storage for PostgresStorage {
+    async fn add_user(&self, user: &UserData, admin: bool) -> Result<bool, ShowError> {
+        let inserted = sqlx::query("INSERT INTO users (userid, username, realname, admin) VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING")
+            .bind(user.userid)
+            .bind(&user.username)
+            .bind(&user.realname)
+            .bind(admin as i32)
+            .execute(&self.db).await?;
+        Ok(inserted.rows_affected() > 0)
+    }
+
+    async fn user_unit(&self, userid: i64) -> Result<Option<String>, ShowError> {
+        Ok(sqlx::query("SELECT unit FROM users WHERE userid=$1")
+            .bind(userid)
+            .fetch_optional(&self.db).await?
+            .and_then(|row| row.get::<Option<String>, _>(0)))
+    }
+
+    async fn set_user_unit(&self, userid: i64, unit: &str) -> Result<(), ShowError> {
+        sqlx::query("INSERT INTO users (userid, unit) VALUES ($1, $2) ON CONFLICT (userid) DO UPDATE SET unit=excluded.unit")
+            .bind(userid)
+            .bind(unit)
+            .execute(&self.db).await?;
+        Ok(())
+    }
+
+    async fn is_admin(&self, userid: i64) -> Result<bool, ShowError> {
+        Ok(sqlx::query("SELECT 1 FROM users WHERE userid=$1 AND admin=1")
+            .bind(userid)
+            .fetch_optional(&self.db).await?
+            .is_some())
+    }
+
+    async fn admins(&self) -> Result<Vec<i64>, ShowError> {
+        Ok(sqlx::query("SELECT userid FROM users WHERE admin=1 ORDER BY id")
+            .fetch_all(&self.db).await?
+            .iter()
+            .map(|row| row.get::<i64, _>(0))
+            .collect())
+    }
+
+    async fn profile(&self, userid: i64) -> Result<Profile, ShowError> {
+        let allergies = sqlx::query("SELECT allergen FROM allergies WHERE userid=$1 ORDER BY allergen")
+            .bind(userid)
+            .fetch_all(&self.db).await?
+            .iter()
+            .map(|row| row.get::<i32, _>(0) as u8)
+            .collect();
+        let avoids = sqlx::query("SELECT tag FROM prefs WHERE userid=$1 AND (until IS NULL OR until>=$2) ORDER BY tag")
+            .bind(userid)
+            .bind(time_now().date_naive().format("%Y-%m-%d").to_string())
+            .fetch_all(&self.db).await?
+            .iter()
+            .map(|row| row.get::<String, _>(0))
+            .collect();
+        Ok(Profile { allergies, avoids })
+    }
+
+    async fn add_allergy(&self, userid: i64, allergen: u8) -> Result<(), ShowError> {
+        sqlx::query("INSERT INTO allergies (userid, allergen) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+            .bind(userid)
+            .bind(allergen as i32)
+            .execute(&self.db).await?;
+        Ok(())
+    }
+
+    async fn del_allergy(&self, userid: i64, allergen: Option<u8>) -> Result<(), ShowError> {
+        sqlx::query("DELETE FROM allergies WHERE userid=$1 AND ($2::INTEGER IS NULL OR allergen=$2)")
+            .bind(userid)
+            .bind(allergen.map(i32::from))
+            .execute(&self.db).await?;
+        Ok(())
+    }
+
+    async fn add_avoid(&self, userid: i64, tag: &str, until: Option<&str>) -> Result<(), ShowError> {
+        sqlx::query("INSERT INTO prefs (userid, tag, until) VALUES ($1, $2, $3) ON CONFLICT (userid, tag) DO UPDATE SET until=EXCLUDED.until")
+            .bind(userid)
+            .bind(tag)
+            .bind(until)
+            .execute(&self.db).await?;
+        Ok(())
+    }
+
+    async fn del_avoid(&self, userid: i64, tag: Option<&str>) -> Result<(), ShowError> {
+        sqlx::query("DELETE FROM prefs WHERE userid=$1 AND ($2::TEXT IS NULL OR tag=$2)")
+            .bind(userid)
+            .bind(tag)
+            .execute(&self.db).await?;
+        Ok(())
+    }
+
+    async fn add_book(&self, userid: i64, timewhen: &str, what: &str) -> Result<(), ShowError> {
+        sqlx::query("INSERT INTO timer (userid, timewhen, what) VALUES ($1, $2, $3) ON CONFLICT (userid, what) DO UPDATE SET timewhen=EXCLUDED.timewhen")
+            .bind(userid)
+            .bind(timewhen)
+            .bind(what)
+            .execute(&self.db).await?;
+        Ok(())
+    }
+
+    async fn del_book(&self, userid: i64, what: &str) -> Result<(), ShowError> {
+        sqlx::query("DELETE FROM timer WHERE userid=$1 AND what=$2")
+            .bind(userid)
+            .bind(what)
+            .execute(&self.db).await?;
+        Ok(())
+    }
+
+    async fn books_of(&self, userid: i64) -> Result<Vec<(String, String)>, ShowError> {
+        Ok(sqlx::query_as::<_, (String, String)>("SELECT what, timewhen FROM timer WHERE userid=$1 ORDER BY timewhen, id")
+            .bind(userid)
+            .fetch_all(&self.db).await?)
+    }
+
+    async fn books_at(&self, timewhen: &str) -> Result<Vec<TimerData>, ShowError> {
+        Ok(sqlx::query_as::<_, TimerData>("SELECT timer.userid, timer.what, users.unit FROM timer LEFT JOIN users ON timer.userid=users.userid WHERE timer.timewhen=$1 ORDER BY timer.id")
+            .bind(timewhen)
+            .fetch_all(&self.db).await?)
+    }
+
+    async fn subscribers(&self, unit: &str, default_unit: &str, kinds: &[&str]) -> Result<Vec<i64>, ShowError> {
+        let kinds: Vec<String> = kinds.iter().map(|k| k.to_string()).collect();
+        Ok(sqlx::query("SELECT DISTINCT timer.userid FROM timer LEFT JOIN users ON timer.userid=users.userid WHERE COALESCE(users.unit, $1)=$2 AND timer.what = ANY($3)")
+            .bind(default_unit)
+            .bind(unit)
+            .bind(kinds)
+            .fetch_all(&self.db).await?
+            .iter()
+            .map(|row| row.get::<i64, _>(0))
+            .collect())
+    }
+
+    async fn last_order(&self, floor: usize) -> Result<Option<usize>, ShowError> {
+        Ok(sqlx::query("SELECT orderidx FROM orders WHERE floor=$1 ORDER BY datestime DESC, id DESC LIMIT 1")
+            .bind(floor as i32)
+            .fetch_optional(&self.db).await?
+            .map(|row| row.get::<i32, _>(0) as usize))
+    }
+
+    async fn insert_order(&self, datestime: NaiveDateTime, floor: usize, orderidx: usize) -> Result<(), ShowError> {
+        sqlx::query("INSERT INTO orders (datestime, floor, ordertxt, orderidx) VALUES ($1, $2, $3, $4)")
+            .bind(datestime.format("%Y-%m-%d %H:%M").to_string())
+            .bind(floor as i32)
+            .bind(ORDERS[orderidx])
+            .bind(orderidx as i32)
+            .execute(&self.db).await?;
+        Ok(())
+    }
+
+    async fn menus(&self, unit: &str, date: NaiveDate) -> Result<Vec<JjamRow>, ShowError> {
+        Ok(sqlx::query_as::<_, JjamRow>("SELECT * FROM menus WHERE unit=$1 AND dates=$2 ORDER BY id")
+            .bind(unit)
+            .bind(date.format("%Y-%m-%d").to_string())
+            .fetch_all(&self.db).await?)
+    }
+
+    async fn menus_from(&self, unit: &str, from: NaiveDate) -> Result<Vec<JjamRow>, ShowError> {
+        Ok(sqlx::query_as::<_, JjamRow>("SELECT * FROM menus WHERE unit=$1 AND dates>=$2 ORDER BY id")
+            .bind(unit)
+            .bind(from.format("%Y-%m-%d").to_string())
+            .fetch_all(&self.db).await?)
+    }
+
+    async fn menu_range(&self, unit: &str) -> Result<Option<MenuRange>, ShowError> {
+        let row = sqlx::query("SELECT MIN(dates), MAX(dates), COUNT(DISTINCT dates), COUNT(*) FROM menus WHERE unit=$1")
+            .bind(unit)
+            .fetch_one(&self.db).await?;
+        Ok(menu_range(row.get(0), row.get(1), row.get(2), row.get(3)))
+    }
+
+    async fn dishes_from(&self, unit: &str, from: NaiveDate) -> Result<Vec<DishRow>, ShowError> {
+        Ok(sqlx::query_as::<_, DishRow>("SELECT dates, meal, name, kcal FROM dishes WHERE unit=$1 AND dates>=$2 ORDER BY dates, id")
+            .bind(unit)
+            .bind(from.format("%Y-%m-%d").to_string())
+            .fetch_all(&self.db).await?)
+    }
+
+    async fn save_menus(&self, unit: &str, dates: &BTreeMap<String, Vec<JjamRow>>) -> Result<(), ShowError> {
+        let mut tx = self.db.begin().await?;
+        for (date, rows) in dates {
+            replace_menus(&mut tx, unit, date, rows).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::storage::tests::check_storage;
+
+    // DATABASE_URL=postgres://... cargo test -- --ignored
+    // 그 DB 안에 테스트용 스키마를 만들어서 돌리고 지움
+    #[tokio::test]
+    #[ignore]
+    async fn postgres_storage() {
+        let Some(url) = std::env::var("DATABASE_URL").ok().filter(|url| url.starts_with("postgres")) else {
+            eprintln!("DATABASE_URL=postgres://... 가 없어서 건너뜀");
+            return;
+        };
+        let schema = format!("jjambot_test_{}", std::process::id());
+        let admin = PgPool::connect(&url).await.unwrap();
+        sqlx::query(&format!("CREATE SCHEMA {}", schema)).execute(&admin).await.unwrap();
+
+        let options = PgConnectOptions::from_str(&url).unwrap().options([("search_path", schema.as_str())]);
+        let storage = PostgresStorage::connect_with(options, &db::memory().await.unwrap()).await.unwrap();
+        check_storage(&storage).await;
+        storage.db.close().await;
+
+        sqlx::query(&format!("DROP SCHEMA {} CASCADE", schema)).execute(&admin).await.unwrap();
+    }
+}
@@ -0,0 +1,224 @@
+// 수집한 식단 검사
+//
+// 원본 데이터에 날짜가 빠지거나, 같은 행이 두 번 오거나, 끼니가 비어 있거나, 칼로리가 엉뚱한 경우가 있어서
+// 오늘 이후 식단을 훑어서 잘못 안내할 만한 부분을 찾음
+use std::collections::BTreeMap;
+
+use chrono::{Duration, NaiveDate};
+
+use crate::{dish::kcal_total, JjamRow, MealType};
+
+// 요리 하나, 끼니 하나로 보기 어려운 칼로리
+const DISH_KCAL_MAX: f64 = 1500.0;
+const MEAL_KCAL_MIN: f64 = 100.0;
+const MEAL_KCAL_MAX: f64 = 2500.0;
+// 올라온 sum_cal 과 요리 칼로리 합이 이 비율 넘게 차이나면 보고
+const SUM_CAL_TOLERANCE: f64 = 0.2;
+
+pub const KINDS: [&str; 6] = ["날짜오류", "날짜누락", "중복", "빈끼니", "칼로리이상", "칼로리누락"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Finding {
+    pub date: String,
+    pub kind: &'static str,
+    pub detail: String,
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} [{}] {}", self.date, self.kind, self.detail)
+    }
+}
+
+// 저장해 둔 종류 이름을 다시 읽을 때
+pub fn kind(name: &str) -> &'static str {
+    KINDS.iter().find(|k| **k == name).copied().unwrap_or("기타")
+}
+
+// from 이후 날짜만 검사. rows 는 저장된 순서 그대로
+pub fn inspect(rows: &[JjamRow], from: NaiveDate) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut dates: BTreeMap<NaiveDate, Vec<&JjamRow>> = BTreeMap::new();
+    for row in rows {
+        match NaiveDate::parse_from_str(&row.dates, "%Y-%m-%d") {
+            Ok(date) if date >= from => dates.entry(date).or_default().push(row),
+            Ok(_) => {},
+            Err(_) => findings.push(Finding { date: row.dates.clone(), kind: "날짜오류", detail: "날짜를 읽을 수 없음".to_string() }),
+        }
+    }
+
+    // 오늘부터 마지막으로 올라온 날짜 사이에 빠진 날
+    if let Some(last) = dates.keys().next_back().copied() {
+        let mut missing: Vec<NaiveDate> = Vec::new();
+        let mut day = from;
+        while day <= last {
+            if !dates.contains_key(&day) {
+                missing.push(day);
+            }
+            day += Duration::days(1);
+        }
+        for (start, end) in ranges(&missing) {
+            let days = (end - start).num_days() + 1;
+            let detail = if days == 1 { "식단 없음".to_string() } else { format!("~ {} {}일간 식단 없음", end, days) };
+            findings.push(Finding { date: start.to_string(), kind: "날짜누락", detail });
+        }
+    }
+
+    for (date, rows) in &dates {
+        let date = date.to_string();
+        let jjams: Vec<JjamRow> = rows.iter().map(|r| (*r).clone()).collect();
+
+        let mut seen: Vec<&JjamRow> = Vec::new();
+        let mut duplicates = 0;
+        for row in rows {
+            if seen.iter().any(|s| same_row(s, row)) {
+                duplicates += 1;
+            } else {
+                seen.push(row);
+            }
+        }
+        if duplicates > 0 {
+            findings.push(Finding { date: date.clone(), kind: "중복", detail: format!("같은 행 {}개 더 있음", duplicates) });
+        }
+
+        let mut day_total = 0.0;
+        for meal in [MealType::Breakfast, MealType::Lunch, MealType::Dinner] {
+            let dishes = meal.dishes(&jjams);
+            if dishes.is_empty() {
+                findings.push(Finding { date: date.clone(), kind: "빈끼니", detail: format!("{} 식단 없음", meal.name()) });
+                continue;
+            }
+            for dish in dishes.iter().filter(|d| d.kcal.is_some_and(|k| k > DISH_KCAL_MAX)) {
+                findings.push(Finding { date: date.clone(), kind: "칼로리이상", detail: format!("{} {}", meal.name(), dish) });
+            }
+            match kcal_total(&dishes) {
+                None => findings.push(Finding { date: date.clone(), kind: "칼로리누락", detail: format!("{} 칼로리 없음", meal.name()) }),
+                Some((total, unknown)) => {
+                    day_total += total;
+                    if unknown > 0 {
+                        findings.push(Finding { date: date.clone(), kind: "칼로리누락", detail: format!("{} 요리 {}개 칼로리 없음", meal.name(), unknown) });
+                    } else if !(MEAL_KCAL_MIN..=MEAL_KCAL_MAX).contains(&total) {
+                        findings.push(Finding { date: date.clone(), kind: "칼로리이상", detail: format!("{} 합계 {:.0}kcal", meal.name(), total) });
+                    }
+                },
+            }
+        }
+        day_total += kcal_total(&MealType::Snack.dishes(&jjams)).map(|(total, _)| total).unwrap_or(0.0);
+
+        // 올라온 하루 합계는 첫 행에만 있는 경우가 많음
+        if let Some(sum_cal) = rows.iter().find_map(|r| r.sum_cal).filter(|k| *k > 0.0) {
+            if day_total > 0.0 && (sum_cal - day_total).abs() / sum_cal > SUM_CAL_TOLERANCE {
+                findings.push(Finding { date: date.clone(), kind: "칼로리이상", detail: format!("하루 합계 {:.0}kcal, 요리 합 {:.0}kcal", sum_cal, day_total) });
+            }
+        }
+    }
+    findings
+}
+
+fn same_row(a: &JjamRow, b: &JjamRow) -> bool {
+    a.brst == b.brst && a.lunc == b.lunc && a.dinr == b.dinr && a.adspcfd == b.adspcfd
+        && a.brst_cal == b.brst_cal && a.lunc_cal == b.lunc_cal && a.dinr_cal == b.dinr_cal && a.adspcfd_cal == b.adspcfd_cal
+}
+
+// 정렬된 날짜를 연속 구간으로 묶음
+fn ranges(days: &[NaiveDate]) -> Vec<(NaiveDate, NaiveDate)> {
+    let mut ranges: Vec<(NaiveDate, NaiveDate)> = Vec::new();
+    for day in days {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + Duration::days(1) == *day => *end = *day,
+            _ => ranges.push((*day, *day)),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(date: &str, brst: &str, lunc: &str, dinr: &str) -> JjamRow {
+        JjamRow {
+            dates: date.to_string(),
+            brst: brst.to_string(),
+            brst_cal: Some(300.0),
+            lunc: lunc.to_string(),
+            lunc_cal: Some(300.0),
+            dinr: dinr.to_string(),
+            dinr_cal: Some(300.0),
+            ..Default::default()
+        }
+    }
+
+    // 보통 하루는 요리마다 한 행씩 여러 행으로 옴
+    fn day(date: &str) -> Vec<JjamRow> {
+        vec![row(date, "쌀밥", "잡곡밥", "쌀밥"), row(date, "미역국", "된장찌개", "김치찌개"), row(date, "김치", "깍두기", "김")]
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn kinds(findings: &[Finding]) -> Vec<(&str, &'static str)> {
+        findings.iter().map(|f| (f.date.as_str(), f.kind)).collect()
+    }
+
+    #[test]
+    fn distinct_rows_per_date_are_fine() {
+        let rows = [day("2024-01-02"), day("2024-01-03")].concat();
+        assert_eq!(inspect(&rows, date("2024-01-02")), Vec::new());
+    }
+
+    #[test]
+    fn missing_dates_are_grouped() {
+        let rows = [day("2024-01-01"), day("2024-01-03"), day("2024-01-07")].concat();
+        let findings = inspect(&rows, date("2024-01-01"));
+        assert_eq!(kinds(&findings), vec![("2024-01-02", "날짜누락"), ("2024-01-04", "날짜누락")]);
+        assert_eq!(findings[0].detail, "식단 없음");
+        assert_eq!(findings[1].detail, "~ 2024-01-06 3일간 식단 없음");
+        // 오늘(from) 까지 올라온 식단이 없으면 오늘부터 빠진 것
+        let findings = inspect(&day("2024-01-03"), date("2024-01-01"));
+        assert_eq!(kinds(&findings), vec![("2024-01-01", "날짜누락")]);
+        assert_eq!(findings[0].detail, "~ 2024-01-02 2일간 식단 없음");
+        // 지난 날짜는 보지 않음
+        assert_eq!(inspect(&rows, date("2024-01-07")), Vec::new());
+        assert_eq!(inspect(&rows, date("2024-01-08")), Vec::new());
+    }
+
+    #[test]
+    fn duplicate_rows_are_counted() {
+        let mut rows = day("2024-01-02");
+        rows.push(rows[1].clone());
+        rows.push(rows[1].clone());
+        // 요리가 같아도 칼로리가 다르면 다른 행
+        let mut other = rows[0].clone();
+        other.brst_cal = Some(310.0);
+        rows.push(other);
+        let findings = inspect(&rows, date("2024-01-02"));
+        assert_eq!(kinds(&findings), vec![("2024-01-02", "중복")]);
+        assert_eq!(findings[0].detail, "같은 행 2개 더 있음");
+    }
+
+    #[test]
+    fn empty_meals_and_bad_dates() {
+        let mut rows: Vec<JjamRow> = day("2024-01-02").into_iter().map(|r| JjamRow { dinr: String::new(), dinr_cal: None, ..r }).collect();
+        rows.push(row("2024-13-01", "쌀밥", "쌀밥", "쌀밥"));
+        let findings = inspect(&rows, date("2024-01-02"));
+        assert_eq!(kinds(&findings), vec![("2024-13-01", "날짜오류"), ("2024-01-02", "빈끼니")]);
+        assert_eq!(findings[1].detail, "저녁 식단 없음");
+    }
+
+    #[test]
+    fn calorie_findings() {
+        let mut rows = day("2024-01-02");
+        rows[0].brst_cal = Some(2000.0);
+        rows[1].lunc_cal = None;
+        rows[0].sum_cal = Some(9000.0);
+        let findings = inspect(&rows, date("2024-01-02"));
+        let details: Vec<(&str, &str)> = findings.iter().map(|f| (f.kind, f.detail.as_str())).collect();
+        assert_eq!(details.len(), 4, "{:?}", details);
+        assert_eq!(details[0].0, "칼로리이상");
+        assert_eq!(details[1], ("칼로리이상", "아침 합계 2600kcal"));
+        assert_eq!(details[2], ("칼로리누락", "점심 요리 1개 칼로리 없음"));
+        assert_eq!(details[3].0, "칼로리이상");
+    }
+}
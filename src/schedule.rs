@@ -0,0 +1,201 @@
+// 식단 수집 시각
+//
+// cron 과 같은 다섯 칸 형식: 분 시 일 월 요일 (요일은 0, 7이 일요일)
+// 칸마다 *, 숫자, 범위(1-5), 간격(*/2, 8-20/4), 쉼표 목록을 씀
+// 예: "0 6,12,18 * * *" 매일 06:00, 12:00, 18:00 / "30 5 * * 1-5" 평일 05:30
+// 일과 요일을 둘 다 지정하면 cron 처럼 둘 중 하나만 맞아도 됨
+use chrono::{Datelike, Duration, NaiveDateTime, NaiveTime, Timelike};
+
+pub struct Schedule {
+    source: String,
+    minutes: Vec<u32>,
+    hours: Vec<u32>,
+    days: Vec<u32>,
+    months: Vec<u32>,
+    weekdays: Vec<u32>,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl std::fmt::Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl std::str::FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!("{}: 분 시 일 월 요일 다섯 칸이 필요합니다", s));
+        };
+        let mut weekdays = parse_field(weekday, 0, 7)?;
+        for d in weekdays.iter_mut() {
+            *d %= 7;
+        }
+        weekdays.sort_unstable();
+        weekdays.dedup();
+        let days = parse_field(day, 1, 31)?;
+
+        let schedule = Schedule {
+            source: fields.join(" "),
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)?,
+            // "*" 뿐 아니라 1-31, 0-6 처럼 전부 고른 칸도 제한이 없는 것으로 봄
+            any_day: days.len() == 31,
+            any_weekday: weekdays.len() == 7,
+            days,
+            months: parse_field(month, 1, 12)?,
+            weekdays,
+        };
+        // 2월 30일처럼 오지 않는 날만 고르면 수집이 영영 멈추므로 미리 막음
+        if schedule.next_after(NaiveDateTime::default()).is_none() {
+            return Err(format!("{}: 해당하는 날짜가 없습니다", s));
+        }
+        Ok(schedule)
+    }
+}
+
+impl Schedule {
+    // after 보다 뒤인 가장 가까운 수집 시각
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let start = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        // 2월 29일만 고른 경우까지 생각해서 8년치를 봄
+        for offset in 0..366 * 8 {
+            let date = start.date().checked_add_signed(Duration::days(offset))?;
+            if !self.matches_date(date) {
+                continue;
+            }
+            for hour in &self.hours {
+                for minute in &self.minutes {
+                    let time = date.and_time(NaiveTime::from_hms_opt(*hour, *minute, 0)?);
+                    if time >= start {
+                        return Some(time);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn matches_date(&self, date: chrono::NaiveDate) -> bool {
+        if !self.months.contains(&date.month()) {
+            return false;
+        }
+        let day = self.days.contains(&date.day());
+        let weekday = self.weekdays.contains(&date.weekday().num_days_from_sunday());
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        }
+    }
+}
+
+fn parse_field(field: &str, min: u32, max: u32) -> Result<Vec<u32>, String> {
+    let mut values = Vec::new();
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|s| *s > 0).ok_or(format!("{}: 잘못된 간격", item))?),
+            None => (item, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((a, b)) => (number(a, min, max)?, number(b, min, max)?),
+                // 5/10 처럼 시작만 쓰면 끝까지
+                None if item.contains('/') => (number(range, min, max)?, max),
+                None => (number(range, min, max)?, number(range, min, max)?),
+            },
+        };
+        if start > end {
+            return Err(format!("{}: 범위가 거꾸로입니다", item));
+        }
+        values.extend((start..=end).step_by(step as usize));
+    }
+    values.sort_unstable();
+    values.dedup();
+    Ok(values)
+}
+
+fn number(value: &str, min: u32, max: u32) -> Result<u32, String> {
+    value.parse::<u32>()
+        .ok()
+        .filter(|v| (min..=max).contains(v))
+        .ok_or(format!("{}: {}~{} 사이 숫자가 필요합니다", value, min, max))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn parse_fields() {
+        let cases: &[(&str, u32, u32, &[u32])] = &[
+            ("*", 0, 3, &[0, 1, 2, 3]),
+            ("5", 0, 59, &[5]),
+            ("1-5", 0, 7, &[1, 2, 3, 4, 5]),
+            ("*/15", 0, 59, &[0, 15, 30, 45]),
+            ("8-20/4", 0, 23, &[8, 12, 16, 20]),
+            ("50/5", 0, 59, &[50, 55]),
+            ("6,12,18", 0, 23, &[6, 12, 18]),
+            ("18,6,6,1-2", 0, 23, &[1, 2, 6, 18]),
+        ];
+        for (field, min, max, expected) in cases {
+            assert_eq!(parse_field(field, *min, *max).as_deref(), Ok(*expected), "{}", field);
+        }
+        for field in ["", "x", "60", "5-1", "*/0", "*/x", "1-", "-1", "1,,2"] {
+            assert!(parse_field(field, 0, 59).is_err(), "{}", field);
+        }
+    }
+
+    #[test]
+    fn parse_schedules() {
+        for source in ["0 6 * * *", "30 5 * * 1-5", "0 6,12,18 * * *", "0 0 29 2 *", "0 0 * * 7"] {
+            let schedule: Schedule = source.parse().unwrap();
+            assert_eq!(schedule.to_string(), source);
+        }
+        // 칸 수가 틀리거나, 범위 밖이거나, 오지 않는 날짜
+        for source in ["", "0 6 * *", "0 6 * * * *", "60 6 * * *", "0 24 * * *", "0 0 0 * *", "0 0 * 13 *", "0 0 * * 8", "0 0 30 2 *", "0 0 31 4,6 *"] {
+            assert!(source.parse::<Schedule>().is_err(), "{}", source);
+        }
+        // 일요일은 0, 7 둘 다
+        let sunday: Schedule = "0 0 * * 0,7".parse().unwrap();
+        assert_eq!(sunday.weekdays, vec![0]);
+    }
+
+    #[test]
+    fn next_after_times() {
+        let cases = [
+            // 같은 날 다음 시각, 정각에는 그 다음
+            ("0 6,12,18 * * *", "2024-03-10 07:00", "2024-03-10 12:00"),
+            ("0 6,12,18 * * *", "2024-03-10 11:59", "2024-03-10 12:00"),
+            ("0 6,12,18 * * *", "2024-03-10 12:00", "2024-03-10 18:00"),
+            // 날짜가 넘어감: 일, 월말, 연말, 윤년
+            ("0 6 * * *", "2024-03-10 06:30", "2024-03-11 06:00"),
+            ("0 6 * * *", "2024-04-30 23:59", "2024-05-01 06:00"),
+            ("0 6 * * *", "2024-12-31 18:00", "2025-01-01 06:00"),
+            ("0 0 31 * *", "2024-04-01 00:00", "2024-05-31 00:00"),
+            ("0 0 29 2 *", "2024-03-01 00:00", "2028-02-29 00:00"),
+            // 평일만 (2024-03-09 는 토요일)
+            ("30 5 * * 1-5", "2024-03-08 06:00", "2024-03-11 05:30"),
+            // 일과 요일을 둘 다 쓰면 둘 중 하나 (1일 또는 월요일)
+            ("0 0 1 * 1", "2024-03-02 00:00", "2024-03-04 00:00"),
+            ("0 0 1 * 1", "2024-03-25 00:00", "2024-04-01 00:00"),
+            // 전부 고른 칸은 "*" 와 같음 (일 1-31 이면 요일만 봄)
+            ("0 0 1-31 * 1", "2024-03-02 00:00", "2024-03-04 00:00"),
+            ("0 0 15 * 0-6", "2024-03-02 00:00", "2024-03-15 00:00"),
+        ];
+        for (source, after, expected) in cases {
+            let schedule: Schedule = source.parse().unwrap();
+            assert_eq!(schedule.next_after(at(after)), Some(at(expected)), "{} after {}", source, after);
+        }
+    }
+}
@@ -0,0 +1,54 @@
+// 텔레그램 핸들러, 알람, 식단 수집이 같이 쓰는 상태
+//
+// 메시지마다 DB 를 새로 열고 data/ 를 훑던 것을 시작할 때 한 번 열어 두고 돌려씀
+// 핸들러에는 teloxide 의 dptree 의존성으로 넣어 줌
+use sqlx::{Pool, Sqlite};
+
+use crate::storage::{self, Storage};
+use crate::{ShowError, JJAM_KEEP_DAYS, JJAM_TOKEN, TG_TOKEN};
+
+pub struct AppState {
+    // data/jjambot.sqlite, 식단 보관함(archive)과 검사 결과(quality)는 DATABASE_URL 과 상관없이 여기에만 둠
+    pub db: Pool<Sqlite>,
+    pub config: Config,
+    // 사용자, 예약, 식사순서, 식단
+    pub storage: Box<dyn Storage>,
+}
+
+impl AppState {
+    // data/jjambot.sqlite 마이그레이션 뒤 DATABASE_URL 에 맞는 저장소를 엶
+    pub async fn init() -> Result<Self, ShowError> {
+        let db = crate::db::init().await?;
+        Ok(Self {
+            storage: storage::open(&db).await?,
+            db,
+            config: Config::from_env(),
+        })
+    }
+
+    // 주어진 저장소로 (테스트에서 MemoryStorage 를 넣을 때)
+    // 보관함, 검사 결과를 쓰는 db 는 data/ 대신 메모리에 새로 만듦
+    #[cfg(test)]
+    pub async fn with_storage(storage: Box<dyn Storage>, config: Config) -> Result<Self, ShowError> {
+        Ok(Self { db: crate::db::memory().await?, config, storage })
+    }
+}
+
+// 환경변수에서 한 번만 읽는 설정 (명령줄 도구로 쓸 때는 없을 수 있어서 빈 값 허용)
+#[derive(Default)]
+pub struct Config {
+    pub tg_token: String,
+    pub jjam_token: String,
+    // 며칠 전 식단까지 남길지 (JJAM_KEEP_DAYS), None 이면 전부
+    pub keep_days: Option<i64>,
+}
+
+impl Config {
+    fn from_env() -> Self {
+        Self {
+            tg_token: std::env::var(TG_TOKEN).unwrap_or_default(),
+            jjam_token: std::env::var(JJAM_TOKEN).unwrap_or_default(),
+            keep_days: std::env::var(JJAM_KEEP_DAYS).ok().and_then(|v| v.parse().ok()).filter(|days| *days >= 0),
+        }
+    }
+}
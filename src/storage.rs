@@ -0,0 +1,530 @@
+// 사용자, 예약, 식사순서, 식단을 읽고 쓰는 곳
+//
+// 핸들러와 알람은 Storage 만 보고, 실제 저장은 DATABASE_URL 에 따라
+// SqliteStorage(data/, 기본) 나 PostgresStorage(postgres://...) 가 함
+// 테스트에서는 data/ 를 건드리지 않는 MemoryStorage(memory.rs) 를 씀
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use chrono::{NaiveDate, NaiveDateTime};
+use sqlx::database::HasArguments;
+use sqlx::{Encode, IntoArguments, Pool, Row, Sqlite, Type};
+
+use crate::db;
+use crate::postgres::PostgresStorage;
+use crate::{time_now, JjamRow, MealType, Profile, ShowError, ORDERS};
+
+const DATABASE_URL: &str = "DATABASE_URL";
+
+// DATABASE_URL 이 postgres:// 면 Postgres, 없거나 sqlite:data/jjambot.sqlite 면 SQLite
+pub async fn open(db: &Pool<Sqlite>) -> Result<Box<dyn Storage>, ShowError> {
+    match std::env::var(DATABASE_URL).ok().filter(|url| !url.is_empty()) {
+        Some(url) if url.starts_with("postgres://") || url.starts_with("postgresql://") => {
+            Ok(Box::new(PostgresStorage::connect(&url, db).await?))
+        },
+        Some(url) if !url.starts_with("sqlite:") => Err(format!("{}: 지원하지 않는 DATABASE_URL (sqlite:, postgres:// 만 가능)", url).into()),
+        // 백업, 복원, 예전 파일 가져오기가 모두 data/jjambot.sqlite 를 보므로 다른 SQLite 파일은 받지 않음
+        Some(url) if sqlite_path(&url) != sqlite_path(db::MAIN_DB) => {
+            Err(format!("{}: SQLite 는 {} 만 쓸 수 있음 (DATABASE_URL 을 비우거나 postgres:// 로)", url, sqlite_path(db::MAIN_DB)).into())
+        },
+        _ => Ok(Box::new(SqliteStorage::new(db.clone()))),
+    }
+}
+
+// sqlite://./data/x.sqlite?mode=rwc -> data/x.sqlite
+fn sqlite_path(url: &str) -> &str {
+    let path = url.strip_prefix("sqlite://").or_else(|| url.strip_prefix("sqlite:")).unwrap_or(url);
+    let path = path.split('?').next().unwrap_or_default();
+    path.strip_prefix("./").unwrap_or(path)
+}
+
+#[derive(sqlx::FromRow, Clone, Debug, Default)]
+pub struct UserData {
+    pub userid: i64,
+    pub username: String,
+    pub realname: String,
+}
+
+// 예약 하나와 그 사용자가 고른 부대 (없으면 기본 부대)
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub struct TimerData {
+    pub userid: i64,
+    pub what: String,
+    pub unit: Option<String>,
+}
+
+// 식단의 요리 하나 (dishes 테이블 한 행)
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub struct DishRow {
+    pub dates: String,
+    // brst, lunc, dinr, adspcfd
+    pub meal: String,
+    pub name: String,
+    pub kcal: Option<f64>,
+}
+
+// 부대별로 받아 둔 식단: 첫 날짜, 마지막 날짜, 날짜 수, 행 수
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MenuRange {
+    pub first: NaiveDate,
+    pub last: NaiveDate,
+    pub dates: i64,
+    pub rows: i64,
+}
+
+#[async_trait]
+pub trait Storage: Send + Sync {
+    // 이미 있는 사용자면 false
+    async fn add_user(&self, user: &UserData, admin: bool) -> Result<bool, ShowError>;
+    async fn user_unit(&self, userid: i64) -> Result<Option<String>, ShowError>;
+    // /start 를 안 한 사용자면 새로 만듦
+    async fn set_user_unit(&self, userid: i64, unit: &str) -> Result<(), ShowError>;
+    async fn is_admin(&self, userid: i64) -> Result<bool, ShowError>;
+    async fn admins(&self) -> Result<Vec<i64>, ShowError>;
+    // 알레르기와 오늘 적용되는 비선호
+    async fn profile(&self, userid: i64) -> Result<Profile, ShowError>;
+    async fn add_allergy(&self, userid: i64, allergen: u8) -> Result<(), ShowError>;
+    // allergen 이 None 이면 전부
+    async fn del_allergy(&self, userid: i64, allergen: Option<u8>) -> Result<(), ShowError>;
+    // until(YYYY-MM-DD) 까지만, None 이면 계속
+    async fn add_avoid(&self, userid: i64, tag: &str, until: Option<&str>) -> Result<(), ShowError>;
+    // tag 가 None 이면 전부
+    async fn del_avoid(&self, userid: i64, tag: Option<&str>) -> Result<(), ShowError>;
+
+    // 같은 종류는 시각만 바뀜
+    async fn add_book(&self, userid: i64, timewhen: &str, what: &str) -> Result<(), ShowError>;
+    async fn del_book(&self, userid: i64, what: &str) -> Result<(), ShowError>;
+    // 사용자의 (what, timewhen) 예약, 시각순
+    async fn books_of(&self, userid: i64) -> Result<Vec<(String, String)>, ShowError>;
+    // timewhen(HH:MM) 에 보낼 예약
+    async fn books_at(&self, timewhen: &str) -> Result<Vec<TimerData>, ShowError>;
+    // unit 식단을 받는 사용자 중 kinds 를 예약한 사람 (부대를 안 고른 사용자는 default_unit)
+    async fn subscribers(&self, unit: &str, default_unit: &str, kinds: &[&str]) -> Result<Vec<i64>, ShowError>;
+
+    // 층(0, 1)별 마지막 식사순서 번호
+    async fn last_order(&self, floor: usize) -> Result<Option<usize>, ShowError>;
+    async fn insert_order(&self, datestime: NaiveDateTime, floor: usize, orderidx: usize) -> Result<(), ShowError>;
+
+    // 그 날짜 식단 행
+    async fn menus(&self, unit: &str, date: NaiveDate) -> Result<Vec<JjamRow>, ShowError>;
+    // from 이후 식단 행, 올라온 순서 그대로
+    async fn menus_from(&self, unit: &str, from: NaiveDate) -> Result<Vec<JjamRow>, ShowError>;
+    // 저장된 식단의 날짜 범위와 크기, 식단이 없으면 None
+    async fn menu_range(&self, unit: &str) -> Result<Option<MenuRange>, ShowError>;
+    // from 이후 요리, 날짜와 끼니 순서대로
+    async fn dishes_from(&self, unit: &str, from: NaiveDate) -> Result<Vec<DishRow>, ShowError>;
+    // 새로 받은 식단에서 바뀐 날짜를 통째로 바꿈 (빈 목록이면 그 날짜 식단 없음)
+    async fn save_menus(&self, unit: &str, dates: &BTreeMap<String, Vec<JjamRow>>) -> Result<(), ShowError>;
+}
+
+pub struct SqliteStorage {
+    db: Pool<Sqlite>,
+}
+
+impl SqliteStorage {
+    pub fn new(db: Pool<Sqlite>) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    async fn add_user(&self, user: &UserData, admin: bool) -> Result<bool, ShowError> {
+        let inserted = sqlx::query("INSERT OR IGNORE INTO users (userid, username, realname, admin) VALUES (?, ?, ?, ?)")
+            .bind(user.userid)
+            .bind(&user.username)
+            .bind(&user.realname)
+            .bind(admin as i64)
+            .execute(&self.db).await?;
+        Ok(inserted.rows_affected() > 0)
+    }
+
+    async fn user_unit(&self, userid: i64) -> Result<Option<String>, ShowError> {
+        Ok(sqlx::query("SELECT unit FROM users WHERE userid=?;")
+            .bind(userid)
+            .fetch_optional(&self.db).await?
+            .and_then(|row| row.get::<Option<String>, _>(0)))
+    }
+
+    async fn set_user_unit(&self, userid: i64, unit: &str) -> Result<(), ShowError> {
+        sqlx::query("INSERT INTO users (userid, unit) VALUES (?, ?) ON CONFLICT(userid) DO UPDATE SET unit=excluded.unit;")
+            .bind(userid)
+            .bind(unit)
+            .execute(&self.db).await?;
+        Ok(())
+    }
+
+    async fn is_admin(&self, userid: i64) -> Result<bool, ShowError> {
+        Ok(sqlx::query("SELECT 1 FROM users WHERE userid=? AND admin=1;")
+            .bind(userid)
+            .fetch_optional(&self.db).await?
+            .is_some())
+    }
+
+    async fn admins(&self) -> Result<Vec<i64>, ShowError> {
+        Ok(sqlx::query("SELECT userid FROM users WHERE admin=1")
+            .fetch_all(&self.db).await?
+            .iter()
+            .map(|row| row.get::<i64, _>(0))
+            .collect())
+    }
+
+    // 기한(until)이 지난 비선호는 뺌
+    async fn profile(&self, userid: i64) -> Result<Profile, ShowError> {
+        let allergies = sqlx::query("SELECT allergen FROM allergies WHERE userid=? ORDER BY allergen;")
+            .bind(userid)
+            .fetch_all(&self.db).await?
+            .iter()
+            .map(|row| row.get::<u8, _>(0))
+            .collect();
+        let avoids = sqlx::query("SELECT tag FROM prefs WHERE userid=? AND (until IS NULL OR until>=?) ORDER BY tag;")
+            .bind(userid)
+            .bind(time_now().date_naive().format("%Y-%m-%d").to_string())
+            .fetch_all(&self.db).await?
+            .iter()
+            .map(|row| row.get::<String, _>(0))
+            .collect();
+        Ok(Profile { allergies, avoids })
+    }
+
+    async fn add_allergy(&self, userid: i64, allergen: u8) -> Result<(), ShowError> {
+        sqlx::query("INSERT OR IGNORE INTO allergies (userid, allergen) VALUES (?, ?);")
+            .bind(userid)
+            .bind(allergen)
+            .execute(&self.db).await?;
+        Ok(())
+    }
+
+    async fn del_allergy(&self, userid: i64, allergen: Option<u8>) -> Result<(), ShowError> {
+        sqlx::query("DELETE FROM allergies WHERE userid=? AND (? IS NULL OR allergen=?);")
+            .bind(userid)
+            .bind(allergen)
+            .bind(allergen)
+            .execute(&self.db).await?;
+        Ok(())
+    }
+
+    async fn add_avoid(&self, userid: i64, tag: &str, until: Option<&str>) -> Result<(), ShowError> {
+        sqlx::query("INSERT OR REPLACE INTO prefs (userid, tag, until) VALUES (?, ?, ?);")
+            .bind(userid)
+            .bind(tag)
+            .bind(until)
+            .execute(&self.db).await?;
+        Ok(())
+    }
+
+    async fn del_avoid(&self, userid: i64, tag: Option<&str>) -> Result<(), ShowError> {
+        sqlx::query("DELETE FROM prefs WHERE userid=? AND (? IS NULL OR tag=?);")
+            .bind(userid)
+            .bind(tag)
+            .bind(tag)
+            .execute(&self.db).await?;
+        Ok(())
+    }
+
+    async fn add_book(&self, userid: i64, timewhen: &str, what: &str) -> Result<(), ShowError> {
+        sqlx::query("INSERT OR REPLACE INTO timer (userid, timewhen, what) VALUES (?, ?, ?);")
+            .bind(userid)
+            .bind(timewhen)
+            .bind(what)
+            .execute(&self.db).await?;
+        Ok(())
+    }
+
+    async fn del_book(&self, userid: i64, what: &str) -> Result<(), ShowError> {
+        sqlx::query("DELETE FROM timer WHERE userid=? AND what=?;")
+            .bind(userid)
+            .bind(what)
+            .execute(&self.db).await?;
+        Ok(())
+    }
+
+    async fn books_of(&self, userid: i64) -> Result<Vec<(String, String)>, ShowError> {
+        Ok(sqlx::query_as::<_, (String, String)>("SELECT what, timewhen FROM timer WHERE userid=? ORDER BY timewhen, id")
+            .bind(userid)
+            .fetch_all(&self.db).await?)
+    }
+
+    async fn books_at(&self, timewhen: &str) -> Result<Vec<TimerData>, ShowError> {
+        Ok(sqlx::query_as::<_, TimerData>("SELECT timer.userid, timer.what, users.unit FROM timer LEFT JOIN users ON timer.userid=users.userid WHERE timer.timewhen=? ORDER BY timer.id")
+            .bind(timewhen)
+            .fetch_all(&self.db).await?)
+    }
+
+    async fn subscribers(&self, unit: &str, default_unit: &str, kinds: &[&str]) -> Result<Vec<i64>, ShowError> {
+        let sql = format!(
+            "SELECT DISTINCT timer.userid FROM timer LEFT JOIN users ON timer.userid=users.userid WHERE COALESCE(users.unit, ?)=? AND timer.what IN ({})",
+            vec!["?"; kinds.len()].join(", ")
+        );
+        let mut query = sqlx::query(&sql).bind(default_unit).bind(unit);
+        for kind in kinds {
+            query = query.bind(*kind);
+        }
+        Ok(query.fetch_all(&self.db).await?
+            .iter()
+            .map(|row| row.get::<i64, _>(0))
+            .collect())
+    }
+
+    async fn last_order(&self, floor: usize) -> Result<Option<usize>, ShowError> {
+        Ok(sqlx::query("SELECT orderidx FROM orders WHERE floor=? ORDER BY datestime DESC LIMIT 1;")
+            .bind(floor as u32)
+            .fetch_optional(&self.db).await?
+            .map(|row| row.get::<u32, _>(0) as usize))
+    }
+
+    async fn insert_order(&self, datestime: NaiveDateTime, floor: usize, orderidx: usize) -> Result<(), ShowError> {
+        sqlx::query("INSERT INTO orders (datestime, floor, ordertxt, orderidx) VALUES (?, ?, ?, ?);")
+            .bind(datestime.format("%Y-%m-%d %H:%M").to_string())
+            .bind(floor as u32)
+            .bind(ORDERS[orderidx])
+            .bind(orderidx as u32)
+            .execute(&self.db).await?;
+        Ok(())
+    }
+
+    async fn menus(&self, unit: &str, date: NaiveDate) -> Result<Vec<JjamRow>, ShowError> {
+        Ok(sqlx::query_as::<_, JjamRow>("SELECT * FROM menus WHERE unit=? AND dates=? ORDER BY id")
+            .bind(unit)
+            .bind(date.format("%Y-%m-%d").to_string())
+            .fetch_all(&self.db).await?)
+    }
+
+    async fn menus_from(&self, unit: &str, from: NaiveDate) -> Result<Vec<JjamRow>, ShowError> {
+        Ok(sqlx::query_as::<_, JjamRow>("SELECT * FROM menus WHERE unit=? AND dates>=? ORDER BY id")
+            .bind(unit)
+            .bind(from.format("%Y-%m-%d").to_string())
+            .fetch_all(&self.db).await?)
+    }
+
+    async fn menu_range(&self, unit: &str) -> Result<Option<MenuRange>, ShowError> {
+        let row = sqlx::query("SELECT MIN(dates), MAX(dates), COUNT(DISTINCT dates), COUNT(*) FROM menus WHERE unit=?")
+            .bind(unit)
+            .fetch_one(&self.db).await?;
+        Ok(menu_range(row.get(0), row.get(1), row.get(2), row.get(3)))
+    }
+
+    async fn dishes_from(&self, unit: &str, from: NaiveDate) -> Result<Vec<DishRow>, ShowError> {
+        Ok(sqlx::query_as::<_, DishRow>("SELECT dates, meal, name, kcal FROM dishes WHERE unit=? AND dates>=? ORDER BY dates, id")
+            .bind(unit)
+            .bind(from.format("%Y-%m-%d").to_string())
+            .fetch_all(&self.db).await?)
+    }
+
+    async fn save_menus(&self, unit: &str, dates: &BTreeMap<String, Vec<JjamRow>>) -> Result<(), ShowError> {
+        let mut tx = self.db.begin().await?;
+        for (date, rows) in dates {
+            replace_menus(&mut tx, unit, date, rows).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+// 부대의 그 날짜 식단(menus)과 요리(dishes)를 통째로 바꿈, rows 가 비면 지우기만 함
+// SQLite, Postgres 저장소가 같이 씀
+pub async fn replace_menus<DB>(tx: &mut sqlx::Transaction<'_, DB>, unit: &str, date: &str, rows: &[JjamRow]) -> Result<(), ShowError>
+where
+    DB: sqlx::Database,
+    for<'c> &'c mut DB::Connection: sqlx::Executor<'c, Database = DB>,
+    for<'a> <DB as HasArguments<'a>>::Arguments: IntoArguments<'a, DB>,
+    for<'a> &'a str: Encode<'a, DB> + Type<DB>,
+    for<'a> Option<f64>: Encode<'a, DB> + Type<DB>,
+{
+    for table in ["menus", "dishes"] {
+        sqlx::query::<DB>(&placeholders::<DB>(&format!("DELETE FROM {} WHERE unit=? AND dates=?", table)))
+            .bind(unit)
+            .bind(date)
+            .execute(&mut **tx).await?;
+    }
+    let insert_menu = placeholders::<DB>("INSERT INTO menus (unit, dates, brst, brst_cal, lunc, lunc_cal, dinr, dinr_cal, adspcfd, adspcfd_cal, sum_cal) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)");
+    for row in rows {
+        sqlx::query::<DB>(&insert_menu)
+            .bind(unit)
+            .bind(row.dates.as_str())
+            .bind(row.brst.as_str())
+            .bind(row.brst_cal)
+            .bind(row.lunc.as_str())
+            .bind(row.lunc_cal)
+            .bind(row.dinr.as_str())
+            .bind(row.dinr_cal)
+            .bind(row.adspcfd.as_str())
+            .bind(row.adspcfd_cal)
+            .bind(row.sum_cal)
+            .execute(&mut **tx).await?;
+    }
+    let insert_dish = placeholders::<DB>("INSERT INTO dishes (unit, dates, meal, name, allergens, kcal) VALUES (?, ?, ?, ?, ?, ?)");
+    for meal in [MealType::Breakfast, MealType::Lunch, MealType::Dinner, MealType::Snack] {
+        for dish in meal.dishes(rows) {
+            let allergens = dish.allergens_str();
+            sqlx::query::<DB>(&insert_dish)
+                .bind(unit)
+                .bind(date)
+                .bind(meal.column())
+                .bind(dish.name.as_str())
+                .bind(allergens.as_str())
+                .bind(dish.kcal)
+                .execute(&mut **tx).await?;
+        }
+    }
+    Ok(())
+}
+
+// SQLite 식 ? 자리표시자를 Postgres 면 $1, $2, ... 로 바꿈 (SQL 안에 ? 문자가 따로 없을 때만 씀)
+fn placeholders<DB: sqlx::Database>(sql: &str) -> String {
+    if DB::NAME != <sqlx::Postgres as sqlx::Database>::NAME {
+        return sql.to_string();
+    }
+    let mut parts = sql.split('?');
+    let mut out = parts.next().unwrap_or_default().to_string();
+    for (n, part) in parts.enumerate() {
+        out = format!("{}${}{}", out, n + 1, part);
+    }
+    out
+}
+
+// MIN(dates), MAX(dates), COUNT(DISTINCT dates), COUNT(*) 결과를 MenuRange 로
+pub fn menu_range(first: Option<String>, last: Option<String>, dates: i64, rows: i64) -> Option<MenuRange> {
+    let first = NaiveDate::parse_from_str(&first?, "%Y-%m-%d").ok()?;
+    let last = NaiveDate::parse_from_str(&last?, "%Y-%m-%d").ok()?;
+    Some(MenuRange { first, last, dates, rows })
+}
+
+#[cfg(test)]
+pub mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::memory::MemoryStorage;
+
+    fn day(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    fn lunch(date: &str, menu: &str) -> JjamRow {
+        JjamRow { dates: date.to_string(), lunc: menu.to_string(), lunc_cal: Some(300.0), ..Default::default() }
+    }
+
+    // 모든 저장소가 똑같이 해야 하는 것 (빈 저장소에서 시작)
+    pub async fn check_storage(s: &dyn Storage) {
+        let user = |userid: i64| UserData { userid, username: format!("user{}", userid), realname: String::new() };
+
+        // 사용자
+        assert!(s.add_user(&user(1), false).await.unwrap());
+        assert!(!s.add_user(&user(1), true).await.unwrap());
+        assert!(s.add_user(&user(2), true).await.unwrap());
+        assert_eq!(s.user_unit(1).await.unwrap(), None);
+        s.set_user_unit(1, "1111").await.unwrap();
+        assert_eq!(s.user_unit(1).await.unwrap().as_deref(), Some("1111"));
+        // /start 를 안 했어도 부대를 고를 수 있음
+        s.set_user_unit(3, "2222").await.unwrap();
+        assert_eq!(s.user_unit(3).await.unwrap().as_deref(), Some("2222"));
+        assert!(!s.is_admin(1).await.unwrap());
+        assert!(s.is_admin(2).await.unwrap());
+        assert_eq!(s.admins().await.unwrap(), [2]);
+
+        // 알레르기, 비선호 (기한이 지난 비선호는 안 보임)
+        for allergen in [5, 2, 5] {
+            s.add_allergy(1, allergen).await.unwrap();
+        }
+        assert_eq!(s.profile(1).await.unwrap().allergies, [2, 5]);
+        s.del_allergy(1, Some(5)).await.unwrap();
+        assert_eq!(s.profile(1).await.unwrap().allergies, [2]);
+        s.del_allergy(1, None).await.unwrap();
+        assert!(s.profile(1).await.unwrap().allergies.is_empty());
+        let today = time_now().date_naive();
+        let yesterday = (today - Duration::days(1)).format("%Y-%m-%d").to_string();
+        let tomorrow = (today + Duration::days(1)).format("%Y-%m-%d").to_string();
+        s.add_avoid(1, "spicy", None).await.unwrap();
+        s.add_avoid(1, "seafood", Some(&tomorrow)).await.unwrap();
+        s.add_avoid(1, "pork", Some(&yesterday)).await.unwrap();
+        assert_eq!(s.profile(1).await.unwrap().avoids, ["seafood", "spicy"]);
+        s.del_avoid(1, Some("spicy")).await.unwrap();
+        assert_eq!(s.profile(1).await.unwrap().avoids, ["seafood"]);
+        s.del_avoid(1, None).await.unwrap();
+        assert!(s.profile(1).await.unwrap().avoids.is_empty());
+        assert!(s.profile(99).await.unwrap().allergies.is_empty());
+
+        // 예약: 같은 종류는 시각만 바뀜, 부대를 안 고른 사용자는 기본 부대
+        s.add_book(1, "11:30", "lunch").await.unwrap();
+        s.add_book(1, "07:15", "breakfast").await.unwrap();
+        s.add_book(1, "11:45", "lunch").await.unwrap();
+        s.add_book(2, "11:45", "lunch").await.unwrap();
+        s.add_book(3, "11:45", "lunch").await.unwrap();
+        assert_eq!(s.books_of(1).await.unwrap(), [
+            ("breakfast".to_string(), "07:15".to_string()),
+            ("lunch".to_string(), "11:45".to_string()),
+        ]);
+        let mut at: Vec<(i64, String, Option<String>)> = s.books_at("11:45").await.unwrap().into_iter()
+            .map(|t| (t.userid, t.what, t.unit))
+            .collect();
+        at.sort();
+        assert_eq!(at, [
+            (1, "lunch".to_string(), Some("1111".to_string())),
+            (2, "lunch".to_string(), None),
+            (3, "lunch".to_string(), Some("2222".to_string())),
+        ]);
+        let mut subscribers = s.subscribers("1111", "1111", &["lunch", "breakfast"]).await.unwrap();
+        subscribers.sort();
+        assert_eq!(subscribers, [1, 2]);
+        assert_eq!(s.subscribers("2222", "1111", &["lunch"]).await.unwrap(), [3]);
+        assert!(s.subscribers("1111", "1111", &["dinner"]).await.unwrap().is_empty());
+        s.del_book(1, "lunch").await.unwrap();
+        assert_eq!(s.books_of(1).await.unwrap(), [("breakfast".to_string(), "07:15".to_string())]);
+
+        // 식사순서: 층마다 마지막 것
+        let at = |time: &str| NaiveDateTime::parse_from_str(&format!("2024-01-02 {}", time), "%Y-%m-%d %H:%M").unwrap();
+        assert_eq!(s.last_order(0).await.unwrap(), None);
+        s.insert_order(at("07:00"), 0, 1).await.unwrap();
+        s.insert_order(at("12:00"), 0, 2).await.unwrap();
+        assert_eq!(s.last_order(0).await.unwrap(), Some(2));
+        assert_eq!(s.last_order(1).await.unwrap(), None);
+
+        // 식단: 날짜별로 통째로 바꾸고, 요리도 같이 바뀜
+        assert_eq!(s.menu_range("1111").await.unwrap(), None);
+        let menus = BTreeMap::from([
+            ("2024-01-02".to_string(), vec![lunch("2024-01-02", "짜장밥(5.6)"), lunch("2024-01-02", "단무지")]),
+            ("2024-01-03".to_string(), vec![lunch("2024-01-03", "비빔밥")]),
+        ]);
+        s.save_menus("1111", &menus).await.unwrap();
+        let lunc = |rows: Vec<JjamRow>| rows.into_iter().map(|r| r.lunc).collect::<Vec<_>>();
+        assert_eq!(lunc(s.menus("1111", day("2024-01-02")).await.unwrap()), ["짜장밥(5.6)", "단무지"]);
+        assert_eq!(lunc(s.menus_from("1111", day("2024-01-03")).await.unwrap()), ["비빔밥"]);
+        assert!(s.menus("2222", day("2024-01-02")).await.unwrap().is_empty());
+        assert_eq!(s.menu_range("1111").await.unwrap(), Some(MenuRange { first: day("2024-01-02"), last: day("2024-01-03"), dates: 2, rows: 3 }));
+        let dishes: Vec<(String, String, String, Option<f64>)> = s.dishes_from("1111", day("2024-01-02")).await.unwrap().into_iter()
+            .map(|d| (d.dates, d.meal, d.name, d.kcal))
+            .collect();
+        assert_eq!(dishes, [
+            ("2024-01-02".to_string(), "lunc".to_string(), "짜장밥".to_string(), Some(300.0)),
+            ("2024-01-02".to_string(), "lunc".to_string(), "단무지".to_string(), Some(300.0)),
+            ("2024-01-03".to_string(), "lunc".to_string(), "비빔밥".to_string(), Some(300.0)),
+        ]);
+
+        s.save_menus("1111", &BTreeMap::from([("2024-01-02".to_string(), Vec::new())])).await.unwrap();
+        assert_eq!(s.menu_range("1111").await.unwrap(), Some(MenuRange { first: day("2024-01-03"), last: day("2024-01-03"), dates: 1, rows: 1 }));
+        assert_eq!(s.dishes_from("1111", day("2024-01-01")).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn memory_storage() {
+        check_storage(&MemoryStorage::new()).await;
+    }
+
+    #[tokio::test]
+    async fn sqlite_storage() {
+        check_storage(&SqliteStorage::new(db::memory().await.unwrap())).await;
+    }
+
+    #[test]
+    fn only_the_main_sqlite_file_is_accepted() {
+        let main = sqlite_path(db::MAIN_DB);
+        assert_eq!(main, "data/jjambot.sqlite");
+        for url in ["sqlite:data/jjambot.sqlite", "sqlite://data/jjambot.sqlite", "sqlite://./data/jjambot.sqlite?mode=rwc"] {
+            assert_eq!(sqlite_path(url), main, "{}", url);
+        }
+        for url in ["sqlite://other/path.db", "sqlite::memory:", "sqlite://data/jjambot.sqlite.bak"] {
+            assert_ne!(sqlite_path(url), main, "{}", url);
+        }
+    }
+}